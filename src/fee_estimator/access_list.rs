@@ -0,0 +1,197 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use ethereum::{AccessList, AccessListItem, TransactionAction};
+use primitive_types::{H160, H256, U256};
+
+use crate::{
+    simulator::{SimulationResult, Simulator},
+    state::StateProvider,
+};
+
+use super::{precompile, validation::checked_mul, Error, Transaction};
+
+/// Gas saved by each storage key in the access list
+/// (cold access 2100 vs access list storage key 1900 + warm access 100).
+const STORAGE_KEY_SAVING: u64 = 100;
+/// Gas cost of an address in the access list.
+const ADDRESS_COST: u64 = 2_400;
+
+/// Result of access list generation.
+#[derive(Debug)]
+pub struct AccessListEstimate {
+    pub access_list: AccessList,
+    /// Estimated fee in WEI without any access list.
+    pub fee_without_access_list: U256,
+    /// Estimated fee in WEI with the generated access list.
+    pub fee_with_access_list: U256,
+}
+
+/// Generates the optimal access list for a transaction (similar to `eth_createAccessList`)
+/// by executing it locally and compares the estimated fee with and without it.
+pub struct AccessListEstimator<S: StateProvider> {
+    simulator: Arc<Simulator<S>>,
+}
+
+impl<S: StateProvider + 'static> AccessListEstimator<S> {
+    pub fn new(simulator: Arc<Simulator<S>>) -> Self {
+        Self { simulator }
+    }
+
+    pub async fn estimate(
+        &self,
+        transaction: Transaction,
+        gas_price: U256,
+    ) -> Result<AccessListEstimate, Error> {
+        if matches!(transaction, Transaction::Legacy(_)) {
+            return Err(Error::AccessListNotSupported);
+        }
+
+        let without_access_list = self
+            .simulator
+            .simulate(&transaction.with_access_list(vec![]), false)
+            .await?;
+        if !without_access_list.exit_reason.is_succeed() {
+            return Err(Error::ExecutionFailed(without_access_list.exit_reason));
+        }
+        let access_list = Self::access_list(&transaction, &without_access_list);
+        let with_access_list = self
+            .simulator
+            .simulate(&transaction.with_access_list(access_list.clone()), false)
+            .await?;
+
+        Ok(AccessListEstimate {
+            access_list,
+            fee_without_access_list: checked_mul(
+                "gas_price",
+                gas_price,
+                without_access_list.gas_used.into(),
+            )?,
+            fee_with_access_list: checked_mul(
+                "gas_price",
+                gas_price,
+                with_access_list.gas_used.into(),
+            )?,
+        })
+    }
+
+    /// Builds the access list from the accessed addresses and storage slots.
+    ///
+    /// Addresses that are warm anyway (sender, recipient / created contract, coinbase and precompiles)
+    /// are only included when their storage keys save more than the cost of the address itself.
+    fn access_list(transaction: &Transaction, result: &SimulationResult) -> AccessList {
+        let mut warm_addresses =
+            BTreeSet::from([result.coinbase, transaction.sender().unwrap_or_default()]);
+        warm_addresses.extend(result.created_address);
+        if let TransactionAction::Call(address) = transaction.action() {
+            warm_addresses.insert(address);
+        }
+
+        let mut storage_keys: BTreeMap<H160, Vec<H256>> = BTreeMap::new();
+        for address in &result.accessed.accessed_addresses {
+            storage_keys.entry(*address).or_default();
+        }
+        for (address, key) in &result.accessed.accessed_storage {
+            storage_keys.entry(*address).or_default().push(*key);
+        }
+
+        storage_keys
+            .into_iter()
+            .filter(|(address, keys)| {
+                if warm_addresses.contains(address) || precompile::is_precompile(address) {
+                    keys.len() as u64 * STORAGE_KEY_SAVING > ADDRESS_COST
+                } else {
+                    true
+                }
+            })
+            .map(|(address, storage_keys)| AccessListItem {
+                address,
+                storage_keys,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use evm::{executor::stack::Accessed, ExitReason, ExitSucceed};
+
+    use crate::fee_estimator::EIP1559Transaction;
+
+    use super::*;
+
+    const SENDER: H160 = H160::repeat_byte(0xaa);
+    const RECIPIENT: H160 = H160::repeat_byte(0xbb);
+    const COLD: H160 = H160::repeat_byte(0xcc);
+
+    fn transaction() -> Transaction {
+        Transaction::EIP1559(EIP1559Transaction {
+            max_priority_fee_per_gas: U256::from(1),
+            max_fee_per_gas: U256::from(20),
+            gas_limit: U256::from(100_000),
+            input: String::new(),
+            action: TransactionAction::Call(RECIPIENT),
+            from: Some(SENDER),
+            value: U256::zero(),
+            runtime_code_size: None,
+            access_list: vec![],
+            blob_count: 0,
+        })
+    }
+
+    /// Returns the access list built for the given accessed addresses with the given number of storage keys.
+    fn build(accessed_keys: &[(H160, u64)]) -> AccessList {
+        let mut accessed = Accessed::default();
+        accessed.access_addresses([SENDER, RECIPIENT, H160::zero()].into_iter());
+        for (address, keys) in accessed_keys {
+            accessed.access_address(*address);
+            accessed.access_storages((0..*keys).map(|key| (*address, H256::from_low_u64_be(key))));
+        }
+        let result = SimulationResult {
+            exit_reason: ExitReason::Succeed(ExitSucceed::Stopped),
+            output: vec![],
+            gas_used: 21_000,
+            created_address: None,
+            accessed,
+            coinbase: H160::zero(),
+            trace: None,
+        };
+        AccessListEstimator::<crate::state::rpc::RpcStateProvider>::access_list(
+            &transaction(),
+            &result,
+        )
+    }
+
+    fn addresses(access_list: &AccessList) -> Vec<H160> {
+        access_list.iter().map(|item| item.address).collect()
+    }
+
+    #[test]
+    fn warm_address_with_few_keys_is_dropped() {
+        // 24 keys save 2400, which doesn't cover the address
+        let access_list = build(&[(RECIPIENT, 24), (H160::from_low_u64_be(1), 13)]);
+        assert!(access_list.is_empty(), "{access_list:?}");
+    }
+
+    #[test]
+    fn warm_address_with_many_keys_is_kept() {
+        let access_list = build(&[(RECIPIENT, 25), (H160::from_low_u64_be(1), 25)]);
+        assert_eq!(
+            addresses(&access_list),
+            vec![H160::from_low_u64_be(1), RECIPIENT]
+        );
+        assert!(access_list.iter().all(|item| item.storage_keys.len() == 25));
+    }
+
+    #[test]
+    fn cold_address_is_always_kept() {
+        let access_list = build(&[(COLD, 0)]);
+        assert_eq!(addresses(&access_list), vec![COLD]);
+        assert!(access_list[0].storage_keys.is_empty());
+
+        let access_list = build(&[(COLD, 3)]);
+        assert_eq!(access_list[0].storage_keys.len(), 3);
+    }
+}
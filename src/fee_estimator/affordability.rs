@@ -0,0 +1,124 @@
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    validation::{checked_add, checked_mul, gwei_to_wei, ValidationError},
+    Transaction,
+};
+
+/// Blob gas used by a single blob (EIP-4844).
+pub const GAS_PER_BLOB: u64 = 131_072;
+
+/// Whether the sender can afford the worst case cost of a transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Affordability {
+    /// Balance of the sender in WEI
+    pub balance: U256,
+    /// Maximum cost of the transaction in WEI (`value + gas_limit * max gas price + blob gas * blob base fee`)
+    pub max_cost: U256,
+    pub affordable: bool,
+    /// Missing amount in WEI, zero if affordable
+    pub shortfall: U256,
+}
+
+impl Affordability {
+    /// Checks the given balance against the cost of the transaction, its blobs (if any) are paid with the given blob base fee (in WEI).
+    pub fn new(
+        balance: U256,
+        transaction: &Transaction,
+        blob_base_fee: U256,
+    ) -> Result<Self, ValidationError> {
+        let max_cost = max_cost(transaction, blob_base_fee)?;
+        Ok(Self {
+            balance,
+            max_cost,
+            affordable: balance >= max_cost,
+            shortfall: max_cost.saturating_sub(balance),
+        })
+    }
+}
+
+/// Returns the maximum amount the sender pays for the transaction in WEI.
+fn max_cost(transaction: &Transaction, blob_base_fee: U256) -> Result<U256, ValidationError> {
+    let (field, max_gas_price) = match transaction {
+        Transaction::Legacy(tx) => ("gas_price", tx.gas_price),
+        Transaction::EIP2930(tx) => ("gas_price", tx.gas_price),
+        Transaction::EIP1559(tx) => ("max_fee_per_gas", tx.max_fee_per_gas),
+    };
+    let max_fee = checked_mul(
+        field,
+        gwei_to_wei(field, max_gas_price)?,
+        transaction.gas_limit(),
+    )?;
+    let blob_gas = checked_mul(
+        "blob_count",
+        transaction.blob_count().into(),
+        GAS_PER_BLOB.into(),
+    )?;
+    let max_fee = checked_add(
+        "blob_count",
+        max_fee,
+        checked_mul("blob_count", blob_gas, blob_base_fee)?,
+    )?;
+    checked_add("value", max_fee, transaction.value())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum::TransactionAction;
+    use primitive_types::H160;
+
+    use crate::fee_estimator::EIP1559Transaction;
+
+    use super::*;
+
+    fn transaction(blob_count: u64) -> Transaction {
+        Transaction::EIP1559(EIP1559Transaction {
+            max_priority_fee_per_gas: U256::from(1),
+            max_fee_per_gas: U256::from(20),
+            gas_limit: U256::from(21_000),
+            input: String::new(),
+            action: TransactionAction::Call(H160::repeat_byte(0x01)),
+            from: Some(H160::repeat_byte(0xaa)),
+            value: U256::from(1_000),
+            runtime_code_size: None,
+            access_list: vec![],
+            blob_count,
+        })
+    }
+
+    #[test]
+    fn max_cost_includes_value_and_gas() {
+        let affordability = Affordability::new(U256::MAX, &transaction(0), U256::from(7)).unwrap();
+
+        // value + gas limit * 20 GWEI
+        assert_eq!(
+            affordability.max_cost,
+            U256::from(1_000u64 + 21_000 * 20_000_000_000)
+        );
+        assert!(affordability.affordable);
+        assert!(affordability.shortfall.is_zero());
+    }
+
+    #[test]
+    fn max_cost_includes_blob_fees() {
+        let balance = U256::from(21_000u64 * 20_000_000_000);
+        let affordability = Affordability::new(balance, &transaction(2), U256::from(7)).unwrap();
+
+        // value + gas limit * 20 GWEI + 2 blobs * blob gas per blob * 7 WEI
+        let blob_fee = 2 * GAS_PER_BLOB * 7;
+        assert_eq!(
+            affordability.max_cost,
+            balance + U256::from(1_000 + blob_fee)
+        );
+        assert!(!affordability.affordable);
+        assert_eq!(affordability.shortfall, U256::from(1_000 + blob_fee));
+    }
+
+    #[test]
+    fn blob_fee_overflow_is_rejected() {
+        let error = Affordability::new(U256::zero(), &transaction(1), U256::MAX).unwrap_err();
+
+        assert_eq!(error.field, "blob_count");
+    }
+}
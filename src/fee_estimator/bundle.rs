@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use alloy::json_abi::JsonAbi;
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::{simulator::Simulator, state::StateProvider};
+
+use super::{
+    ensure_success,
+    validation::{checked_add, checked_mul},
+    Error, Transaction,
+};
+
+/// Estimated cost of a single transaction in a bundle.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleTransactionEstimate {
+    pub gas_used: U256,
+    /// Estimated fee in WEI
+    pub fee: U256,
+}
+
+/// Result of bundle estimation.
+#[derive(Debug)]
+pub struct BundleEstimate {
+    pub transactions: Vec<BundleTransactionEstimate>,
+    /// Sum of all the transaction fees in WEI.
+    pub total_fee: U256,
+}
+
+/// Estimates an ordered list of transactions of the same sender,
+/// where each transaction is executed on top of the state changes of the previous ones.
+pub struct BundleEstimator<S: StateProvider> {
+    simulator: Arc<Simulator<S>>,
+}
+
+impl<S: StateProvider + 'static> BundleEstimator<S> {
+    pub fn new(simulator: Arc<Simulator<S>>) -> Self {
+        Self { simulator }
+    }
+
+    /// Estimates the given transactions, `gas_prices` are the gas prices in WEI for each transaction.
+    /// Fails if any of the transactions would fail, custom errors are decoded with the given ABI (if any).
+    pub async fn estimate(
+        &self,
+        transactions: &[Transaction],
+        gas_prices: &[U256],
+        abi: Option<&JsonAbi>,
+    ) -> Result<BundleEstimate, Error> {
+        let Some(sender) = transactions.first().map(Transaction::sender) else {
+            return Err(Error::EmptyBundle);
+        };
+        if let Some(index) = transactions.iter().position(|tx| tx.sender() != sender) {
+            return Err(Error::BundleSenderMismatch { index });
+        }
+
+        let results = self.simulator.simulate_bundle(transactions).await?;
+
+        let mut estimates = Vec::with_capacity(results.len());
+        let mut total_fee = U256::zero();
+        for (index, (result, gas_price)) in results.into_iter().zip(gas_prices).enumerate() {
+            let result =
+                ensure_success(result, abi).map_err(|error| Error::BundleTransactionFailed {
+                    index,
+                    error: Box::new(error),
+                })?;
+            let fee = checked_mul("gas_price", *gas_price, result.gas_used.into())?;
+            total_fee = checked_add("gas_price", total_fee, fee)?;
+            estimates.push(BundleTransactionEstimate {
+                gas_used: result.gas_used.into(),
+                fee,
+            });
+        }
+
+        Ok(BundleEstimate {
+            transactions: estimates,
+            total_fee,
+        })
+    }
+}
@@ -0,0 +1,227 @@
+use ethereum::TransactionAction;
+use evm_disassembler::{Opcode, Operation};
+use primitive_types::U256;
+
+/// Minimum size of a Solidity CBOR metadata trailer (map header + one key + 2 bytes length).
+const MIN_METADATA_LENGTH: usize = 4;
+
+/// Returns the given bytecode without the Solidity CBOR metadata trailer (if there is any).
+///
+/// Solidity appends a CBOR encoded map (ipfs/bzzr hash, compiler version etc...) to the end of the bytecode
+/// and the last 2 bytes (big-endian) are the length of that map.
+pub fn strip_metadata(code: &[u8]) -> &[u8] {
+    if code.len() < MIN_METADATA_LENGTH {
+        return code;
+    }
+
+    let metadata_length = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]) as usize;
+    if metadata_length < 2 {
+        return code;
+    }
+    let Some(metadata_start) = code.len().checked_sub(metadata_length + 2) else {
+        return code;
+    };
+
+    // CBOR map with 1-5 entries, where the first key is a text string (like "ipfs", "bzzr1" or "solc")
+    let map_header = code[metadata_start];
+    let first_key_header = code[metadata_start + 1];
+    if (0xa1..=0xa5).contains(&map_header) && (0x61..=0x77).contains(&first_key_header) {
+        return &code[..metadata_start];
+    }
+
+    code
+}
+
+/// Disassembles the given bytecode and returns only the operations that can be executed.
+///
+/// The following parts are excluded:
+/// - in case of contract creation the Solidity CBOR metadata trailer
+///   and everything after the constructor's `RETURN` (runtime code, metadata, constructor arguments)
+/// - unreachable code after terminating operations until the next `JUMPDEST` (data segments)
+pub fn executable_operations(
+    code: &[u8],
+    action: TransactionAction,
+) -> Result<Vec<Operation>, eyre::Report> {
+    let operations = match action {
+        // the runtime code is just data for the constructor
+        TransactionAction::Create => {
+            let operations = evm_disassembler::disassemble_bytes(strip_metadata(code).to_vec())?;
+            match constructor_return_index(&operations) {
+                Some(return_index) => operations.into_iter().take(return_index + 1).collect(),
+                None => operations,
+            }
+        }
+        // call data is not bytecode, so it has no metadata either
+        TransactionAction::Call(_) => evm_disassembler::disassemble_bytes(code.to_vec())?,
+    };
+
+    let mut reachable = true;
+    Ok(operations
+        .into_iter()
+        .filter(|operation| {
+            if operation.opcode == Opcode::JUMPDEST {
+                reachable = true;
+            }
+            let include = reachable;
+            if is_terminating(operation.opcode) {
+                reachable = false;
+            }
+            include
+        })
+        .collect())
+}
+
+/// Returns the size of the runtime code deployed by the given contract creation code.
+///
+/// The size is read from the `PUSH size DUP1 PUSH offset PUSH dest CODECOPY` sequence Solidity generates
+/// in the constructor, otherwise everything after the constructor is considered to be runtime code.
+/// The size is returned as read (up to 32 bytes), the caller has to check whether it fits in the code size limits.
+pub fn runtime_code_size(code: &[u8]) -> Result<U256, eyre::Report> {
+    let operations = evm_disassembler::disassemble_bytes(code.to_vec())?;
+    let Some(return_index) = constructor_return_index(&operations) else {
+        return Ok(U256::zero());
+    };
+
+    let codecopy_index = operations[..return_index]
+        .iter()
+        .rposition(|operation| operation.opcode == Opcode::CODECOPY);
+    if let Some(codecopy_index) = codecopy_index.filter(|index| *index >= 4) {
+        let size_operation = &operations[codecopy_index - 4];
+        if operations[codecopy_index - 3].opcode == Opcode::DUP1 && !size_operation.input.is_empty()
+        {
+            return Ok(U256::from_big_endian(&size_operation.input));
+        }
+    }
+
+    // runtime code starts right after the INVALID operation following the constructor's RETURN
+    let runtime_code_offset = operations[return_index + 1].offset as usize + 1;
+    Ok(code.len().saturating_sub(runtime_code_offset).into())
+}
+
+/// Returns the index of the constructor's closing `RETURN`, that is followed by an `INVALID` in Solidity generated code.
+fn constructor_return_index(operations: &[Operation]) -> Option<usize> {
+    operations.windows(2).position(|window| {
+        window[0].opcode == Opcode::RETURN && window[1].opcode == Opcode::INVALID
+    })
+}
+
+/// Returns true if the execution can't continue with the next operation after the given opcode.
+fn is_terminating(opcode: Opcode) -> bool {
+    matches!(
+        opcode,
+        Opcode::STOP
+            | Opcode::JUMP
+            | Opcode::RETURN
+            | Opcode::REVERT
+            | Opcode::INVALID
+            | Opcode::SELFDESTRUCT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use primitive_types::H160;
+
+    use super::*;
+
+    /// Init code of a `Storage` contract (`store(uint256)` / `retrieve()`) compiled by solc 0.8.26.
+    const STORAGE_INIT_CODE: &str = "6080604052348015600e575f80fd5b506101438061001c5f395ff3fe608060405234801561000f575f80fd5b5060043610610034575f3560e01c80632e64cec1146100385780636057361d14610056575b5f80fd5b610040610072565b60405161004d919061009b565b60405180910390f35b610070600480360381019061006b91906100e2565b61007a565b005b5f8054905090565b805f8190555050565b5f819050919050565b61009581610083565b82525050565b5f6020820190506100ae5f83018461008c565b92915050565b5f80fd5b6100c181610083565b81146100cb575f80fd5b50565b5f813590506100dc816100b8565b92915050565b5f602082840312156100f7576100f66100b4565b5b5f610104848285016100ce565b9150509291505056fea26469706673582212209a0dd35336aff1eb3eeb11db76aa60a1427a12c1b92f945ea8c8d1dfa337cf2264736f6c634300081a0033";
+
+    /// Size of the CBOR metadata trailer of [`STORAGE_INIT_CODE`] including its 2 bytes length.
+    const STORAGE_METADATA_SIZE: usize = 0x33 + 2;
+
+    fn storage_init_code() -> Vec<u8> {
+        hex::decode(STORAGE_INIT_CODE).unwrap()
+    }
+
+    #[test]
+    fn strip_metadata_removes_solc_trailer() {
+        let code = storage_init_code();
+        let stripped = strip_metadata(&code);
+        assert_eq!(stripped.len(), code.len() - STORAGE_METADATA_SIZE);
+        // the runtime code ends with INVALID before the metadata
+        assert_eq!(stripped.last(), Some(&0xfe));
+    }
+
+    #[test]
+    fn strip_metadata_keeps_code_without_trailer() {
+        let code = hex::decode("6080604052").unwrap();
+        assert_eq!(strip_metadata(&code), code.as_slice());
+    }
+
+    #[test]
+    fn create_operations_end_with_constructor_return() {
+        let operations =
+            executable_operations(&storage_init_code(), TransactionAction::Create).unwrap();
+        assert_eq!(operations.last().unwrap().opcode, Opcode::RETURN);
+        // PUSH2 0x0143 DUP1 PUSH2 0x001c PUSH0 CODECOPY PUSH0 RETURN
+        assert_eq!(operations.last().unwrap().offset, 0x1a);
+    }
+
+    #[test]
+    fn call_data_is_not_stripped() {
+        // PUSH1 0x01 PUSH1 0x01 followed by a metadata like trailer: LOG1 PUSH2 0x6101 STOP <length 0x0004>
+        let code = hex::decode("60016001a161610100 04".replace(' ', "")).unwrap();
+        assert_eq!(strip_metadata(&code).len(), 4);
+
+        let operations =
+            executable_operations(&code, TransactionAction::Call(H160::zero())).unwrap();
+        let opcodes: Vec<Opcode> = operations
+            .iter()
+            .map(|operation| operation.opcode)
+            .collect();
+        assert_eq!(
+            opcodes,
+            vec![
+                Opcode::PUSH1,
+                Opcode::PUSH1,
+                Opcode::LOG1,
+                Opcode::PUSH2,
+                Opcode::STOP
+            ]
+        );
+
+        let operations = executable_operations(&code, TransactionAction::Create).unwrap();
+        assert_eq!(operations.len(), 2);
+    }
+
+    #[test]
+    fn unreachable_data_is_skipped_until_jumpdest() {
+        // PUSH1 0x00 JUMP <data: SSTORE SSTORE> JUMPDEST STOP
+        let code = hex::decode("6000565555 5b00".replace(' ', "")).unwrap();
+        let operations =
+            executable_operations(&code, TransactionAction::Call(H160::zero())).unwrap();
+        let opcodes: Vec<Opcode> = operations
+            .iter()
+            .map(|operation| operation.opcode)
+            .collect();
+        assert_eq!(
+            opcodes,
+            vec![Opcode::PUSH1, Opcode::JUMP, Opcode::JUMPDEST, Opcode::STOP]
+        );
+    }
+
+    #[test]
+    fn runtime_code_size_is_read_from_codecopy() {
+        assert_eq!(
+            runtime_code_size(&storage_init_code()).unwrap(),
+            U256::from(0x143)
+        );
+    }
+
+    #[test]
+    fn runtime_code_size_doesnt_overflow() {
+        // PUSH32 0xff..ff DUP1 PUSH1 0x00 PUSH1 0x00 CODECOPY PUSH1 0x00 RETURN INVALID
+        let code =
+            hex::decode(format!("7f{}80600060003960 00f3fe", "ff".repeat(32)).replace(' ', ""))
+                .unwrap();
+        assert_eq!(runtime_code_size(&code).unwrap(), U256::MAX);
+    }
+
+    #[test]
+    fn runtime_code_size_falls_back_to_code_after_constructor() {
+        // PUSH1 0x00 DUP1 RETURN INVALID <3 bytes of runtime code>
+        let code = hex::decode("600080f3fe600000").unwrap();
+        assert_eq!(runtime_code_size(&code).unwrap(), U256::from(3));
+    }
+}
@@ -0,0 +1,186 @@
+use ethereum::{AccessList, TransactionAction};
+use primitive_types::{H160, U256};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    validation::{checked_add, checked_mul, ValidationError},
+    EIP1559Transaction, EIP2930Transaction, LegacyTransaction, Transaction,
+};
+
+/// A transaction without its type specific fee fields, so it can be priced as any transaction type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionIntent {
+    /// Recipient of the transaction, contract creation if not set
+    #[serde(default)]
+    pub to: Option<H160>,
+    #[serde(alias = "data")]
+    pub input: String,
+    #[serde(default)]
+    pub value: U256,
+    pub gas_limit: U256,
+    #[serde(default)]
+    pub from: Option<H160>,
+    /// Priority fee / gas in GWEI that is paid on top of the current base fee
+    #[serde(default)]
+    pub max_priority_fee_per_gas: U256,
+    /// Access list used for EIP-2930 and EIP-1559 transactions
+    #[serde(default)]
+    pub access_list: AccessList,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionType {
+    Legacy,
+    EIP2930,
+    EIP1559,
+}
+
+/// Estimated costs of the same intent as different transaction types in WEI.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionTypeComparison {
+    pub legacy: U256,
+    pub eip2930: U256,
+    pub eip1559: U256,
+}
+
+impl TransactionTypeComparison {
+    /// Returns the cheapest transaction type, newer types are preferred when costs are equal.
+    pub fn cheapest(&self) -> TransactionType {
+        [
+            (TransactionType::EIP1559, self.eip1559),
+            (TransactionType::EIP2930, self.eip2930),
+            (TransactionType::Legacy, self.legacy),
+        ]
+        .into_iter()
+        .reduce(|cheapest, current| {
+            if current.1 < cheapest.1 {
+                current
+            } else {
+                cheapest
+            }
+        })
+        .map(|(transaction_type, _)| transaction_type)
+        .unwrap_or(TransactionType::EIP1559)
+    }
+}
+
+impl TransactionIntent {
+    fn action(&self) -> TransactionAction {
+        match self.to {
+            Some(address) => TransactionAction::Call(address),
+            None => TransactionAction::Create,
+        }
+    }
+
+    /// Creates all the transaction types from the intent, where `base_fee_per_gas` is the current base fee in GWEI.
+    ///
+    /// Every type pays the same price per gas: base fee + priority fee. Legacy and EIP-2930 transactions pay it as
+    /// gas price, EIP-1559 transactions allow up to twice the base fee (+ priority fee), so they stay valid
+    /// if the base fee rises in the next blocks.
+    pub fn transactions(
+        &self,
+        base_fee_per_gas: U256,
+    ) -> Result<(Transaction, Transaction, Transaction), ValidationError> {
+        let gas_price = checked_add(
+            "max_priority_fee_per_gas",
+            base_fee_per_gas,
+            self.max_priority_fee_per_gas,
+        )?;
+        let max_fee_per_gas = checked_add(
+            "max_priority_fee_per_gas",
+            checked_mul("max_fee_per_gas", base_fee_per_gas, U256::from(2))?,
+            self.max_priority_fee_per_gas,
+        )?;
+        let legacy = Transaction::Legacy(LegacyTransaction {
+            gas_price,
+            gas_limit: self.gas_limit,
+            input: self.input.clone(),
+            action: self.action(),
+            runtime_code_size: None,
+            from: self.from,
+            value: self.value,
+        });
+        let eip2930 = Transaction::EIP2930(EIP2930Transaction {
+            gas_price,
+            gas_limit: self.gas_limit,
+            input: self.input.clone(),
+            action: self.action(),
+            access_list: self.access_list.clone(),
+            runtime_code_size: None,
+            from: self.from,
+            value: self.value,
+        });
+        let eip1559 = Transaction::EIP1559(EIP1559Transaction {
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas_limit: self.gas_limit,
+            input: self.input.clone(),
+            action: self.action(),
+            access_list: self.access_list.clone(),
+            runtime_code_size: None,
+            from: self.from,
+            value: self.value,
+            blob_count: 0,
+        });
+
+        Ok((legacy, eip2930, eip1559))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intent(max_priority_fee_per_gas: u64) -> TransactionIntent {
+        TransactionIntent {
+            to: Some(H160::repeat_byte(0x01)),
+            input: String::new(),
+            value: U256::zero(),
+            gas_limit: U256::from(21_000),
+            from: None,
+            max_priority_fee_per_gas: max_priority_fee_per_gas.into(),
+            access_list: vec![],
+        }
+    }
+
+    #[test]
+    fn all_types_pay_base_fee_and_priority_fee() {
+        let (legacy, eip2930, eip1559) = intent(2).transactions(U256::from(10)).unwrap();
+        let Transaction::Legacy(legacy) = legacy else {
+            panic!("legacy transaction expected");
+        };
+        let Transaction::EIP2930(eip2930) = eip2930 else {
+            panic!("EIP-2930 transaction expected");
+        };
+        let Transaction::EIP1559(eip1559) = eip1559 else {
+            panic!("EIP-1559 transaction expected");
+        };
+
+        assert_eq!(legacy.gas_price, U256::from(12));
+        assert_eq!(eip2930.gas_price, U256::from(12));
+        assert_eq!(eip1559.max_priority_fee_per_gas, U256::from(2));
+        assert_eq!(eip1559.max_fee_per_gas, U256::from(22));
+    }
+
+    #[test]
+    fn overflowing_fees_are_rejected() {
+        assert!(intent(1).transactions(U256::MAX).is_err());
+    }
+
+    #[test]
+    fn cheapest_prefers_newer_types_on_equal_costs() {
+        let comparison = TransactionTypeComparison {
+            legacy: U256::from(10),
+            eip2930: U256::from(10),
+            eip1559: U256::from(10),
+        };
+        assert_eq!(comparison.cheapest(), TransactionType::EIP1559);
+
+        let comparison = TransactionTypeComparison {
+            legacy: U256::from(9),
+            eip2930: U256::from(10),
+            eip1559: U256::from(10),
+        };
+        assert_eq!(comparison.cheapest(), TransactionType::Legacy);
+    }
+}
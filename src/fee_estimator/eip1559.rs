@@ -1,32 +1,48 @@
-use std::sync::Arc;
-
 use evm_runtime::Config;
 use primitive_types::U256;
 
-use crate::gas_price_collector::Collector;
+use crate::gas_price_collector::FeeSnapshot;
 
-use super::{gas_used_estimator::GasUsedEstimator, EIP1559Transaction, Error, Transaction};
+use super::{
+    gas_used_estimator::GasUsedEstimator,
+    validation::{checked_add, checked_mul, gwei_to_wei, to_u64},
+    EIP1559Transaction, Error, Transaction,
+};
 
-pub struct EIP1559TransactionEstimator<C: Collector> {
-    gas_price_collector: Arc<C>,
+/// Estimates EIP-1559 transactions with the base fee of the given fee snapshot.
+pub struct EIP1559TransactionEstimator<'a> {
+    snapshot: &'a FeeSnapshot,
 }
 
-impl<C: Collector> EIP1559TransactionEstimator<C> {
-    pub fn new(gas_price_collector: Arc<C>) -> Self {
-        Self {
-            gas_price_collector,
-        }
+impl<'a> EIP1559TransactionEstimator<'a> {
+    pub fn new(snapshot: &'a FeeSnapshot) -> Self {
+        Self { snapshot }
+    }
+
+    pub fn estimate(&self, transaction: EIP1559Transaction) -> Result<U256, Error> {
+        let estimator = GasUsedEstimator::new(
+            Config::cancun(),
+            to_u64("gas_limit", transaction.gas_limit)?,
+        );
+        let gas_price = self.gas_price(&transaction)?;
+        let gas_used = estimator.estimate(Transaction::EIP1559(transaction))?;
+        let fee = checked_mul("max_fee_per_gas", gas_price, gas_used.into())?;
+
+        Ok(fee)
     }
 
-    pub async fn estimate(&self, transaction: EIP1559Transaction) -> Result<U256, Error> {
-        let max_fee_per_gas = transaction
-            .max_fee_per_gas
-            .saturating_mul(U256::from(1_000_000_000));
-        let estimator = GasUsedEstimator::new(Config::london(), transaction.gas_limit.as_u64());
-        let gas_price = transaction
-            .max_priority_fee_per_gas
-            .saturating_mul(U256::from(1_000_000_000))
-            .saturating_add(self.gas_price_collector.gas_price().await);
+    /// Returns the gas price of the given transaction in WEI (priority fee + base fee of the fee snapshot),
+    /// fails if max fee / gas doesn't cover it.
+    pub fn gas_price(&self, transaction: &EIP1559Transaction) -> Result<U256, Error> {
+        let max_fee_per_gas = gwei_to_wei("max_fee_per_gas", transaction.max_fee_per_gas)?;
+        let gas_price = checked_add(
+            "max_priority_fee_per_gas",
+            gwei_to_wei(
+                "max_priority_fee_per_gas",
+                transaction.max_priority_fee_per_gas,
+            )?,
+            self.snapshot.base_fee_or_gas_price(),
+        )?;
 
         if max_fee_per_gas.lt(&gas_price) {
             return Err(Error::MaxFeePerGasTooLow {
@@ -35,9 +51,6 @@ impl<C: Collector> EIP1559TransactionEstimator<C> {
             });
         }
 
-        let gas_used = estimator.estimate(Transaction::EIP1559(transaction))?;
-        let fee = gas_price.saturating_mul(gas_used.into());
-
-        Ok(fee)
+        Ok(gas_price)
     }
 }
@@ -1,13 +1,32 @@
 use std::sync::Arc;
 
+use alloy::json_abi::JsonAbi;
 use ethereum::{AccessList, TransactionAction};
-use primitive_types::U256;
+use evm::ExitReason;
+use primitive_types::{H160, U256};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::gas_price_collector::Collector;
+use crate::{
+    gas_price_collector::{Collector, FeeSnapshot, HistoryPoint},
+    simulator::{self, SimulationResult, Simulator},
+    state::{
+        snapshot::{SnapshotStateProvider, StateSnapshot},
+        StateProvider,
+    },
+};
 
-use super::{eip1559, eip2930, gas_used_estimator, legacy};
+use super::{
+    access_list::{self, AccessListEstimate},
+    affordability::Affordability,
+    bundle::{BundleEstimate, BundleEstimator},
+    comparison::{TransactionIntent, TransactionTypeComparison},
+    eip1559, eip2930, gas_used_estimator, legacy,
+    revert::RevertReason,
+    simulation::SimulationOutcome,
+    staleness::{FeeDataInfo, StalenessPolicy},
+    validation::{self, ValidationError},
+};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -15,9 +34,62 @@ pub enum Error {
     InputEstimator(#[from] gas_used_estimator::Error),
     #[error("Max fee / gas is too low: {current}, calculated: {calculated}")]
     MaxFeePerGasTooLow { current: U256, calculated: U256 },
+    #[error("Simulator error: {0}")]
+    Simulator(#[from] simulator::Error),
+    #[error("Transaction execution failed: {0:?}")]
+    ExecutionFailed(ExitReason),
+    #[error("Transaction reverted: {0}")]
+    Reverted(RevertReason),
+    #[error("Access lists are not supported for legacy transactions")]
+    AccessListNotSupported,
+    #[error("{0}")]
+    Validation(#[from] ValidationError),
+    #[error("Gas price is too low: {current}, base fee: {base_fee}")]
+    GasPriceTooLow { current: U256, base_fee: U256 },
+    #[error("No gas price is collected at or before {0:?}")]
+    NoGasPriceHistory(HistoryPoint),
+    #[error("No gas price can be collected at {0:?} yet")]
+    HistoryPointOutOfRange(HistoryPoint),
+    #[error("Blob base fee is unknown, the chain doesn't support blobs")]
+    BlobBaseFeeUnknown,
+    #[error("No fresh gas price is collected yet")]
+    GasPriceNotReady,
+    #[error("Fee data is stale: collected {age}s ago, maximum allowed age is {max_age}s")]
+    StaleFeeData { age: u64, max_age: u64 },
+    #[error("Bundle has no transactions")]
+    EmptyBundle,
+    #[error("Bundle transaction {index} has a different sender than the first transaction")]
+    BundleSenderMismatch { index: usize },
+    #[error("Bundle transaction {index} failed: {error}")]
+    BundleTransactionFailed { index: usize, error: Box<Error> },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Error {
+    /// Returns the validation error if the request was rejected because of an invalid field.
+    pub fn validation_error(&self) -> Option<&ValidationError> {
+        match self {
+            Error::Validation(error) => Some(error),
+            Error::InputEstimator(gas_used_estimator::Error::Validation(error)) => Some(error),
+            Error::Simulator(simulator::Error::Validation(error)) => Some(error),
+            Error::BundleTransactionFailed { error, .. } => error.validation_error(),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the request was rejected because it is invalid, not because it can't be estimated.
+    pub fn is_invalid_request(&self) -> bool {
+        self.validation_error().is_some()
+            || matches!(
+                self,
+                Error::AccessListNotSupported
+                    | Error::HistoryPointOutOfRange(_)
+                    | Error::EmptyBundle
+                    | Error::BundleSenderMismatch { .. }
+            )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Transaction {
     /// Legacy transaction type
     Legacy(LegacyTransaction),
@@ -27,56 +99,738 @@ pub enum Transaction {
     EIP1559(EIP1559Transaction),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LegacyTransaction {
     pub gas_price: U256,
     pub gas_limit: U256,
     pub input: String,
     pub action: TransactionAction,
+    /// Sender of the transaction, used when the transaction is executed locally
+    #[serde(default)]
+    pub from: Option<H160>,
+    /// Value transferred in WEI
+    #[serde(default)]
+    pub value: U256,
+    /// Size of the deployed runtime code in case of contract creation, derived from the input if not set
+    #[serde(default)]
+    pub runtime_code_size: Option<U256>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EIP2930Transaction {
     pub gas_price: U256,
     pub gas_limit: U256,
     pub input: String,
     pub action: TransactionAction,
+    /// Sender of the transaction, used when the transaction is executed locally
+    #[serde(default)]
+    pub from: Option<H160>,
+    /// Value transferred in WEI
+    #[serde(default)]
+    pub value: U256,
+    /// Size of the deployed runtime code in case of contract creation, derived from the input if not set
+    #[serde(default)]
+    pub runtime_code_size: Option<U256>,
     pub access_list: AccessList,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EIP1559Transaction {
     pub max_priority_fee_per_gas: U256,
     pub max_fee_per_gas: U256,
     pub gas_limit: U256,
     pub input: String,
     pub action: TransactionAction,
+    /// Sender of the transaction, used when the transaction is executed locally
+    #[serde(default)]
+    pub from: Option<H160>,
+    /// Value transferred in WEI
+    #[serde(default)]
+    pub value: U256,
+    /// Size of the deployed runtime code in case of contract creation, derived from the input if not set
+    #[serde(default)]
+    pub runtime_code_size: Option<U256>,
     pub access_list: AccessList,
+    /// Number of blobs carried by the transaction (EIP-4844)
+    #[serde(default)]
+    pub blob_count: u64,
+}
+
+impl Transaction {
+    pub fn sender(&self) -> Option<H160> {
+        match self {
+            Transaction::Legacy(tx) => tx.from,
+            Transaction::EIP2930(tx) => tx.from,
+            Transaction::EIP1559(tx) => tx.from,
+        }
+    }
+
+    pub fn value(&self) -> U256 {
+        match self {
+            Transaction::Legacy(tx) => tx.value,
+            Transaction::EIP2930(tx) => tx.value,
+            Transaction::EIP1559(tx) => tx.value,
+        }
+    }
+
+    pub fn gas_limit(&self) -> U256 {
+        match self {
+            Transaction::Legacy(tx) => tx.gas_limit,
+            Transaction::EIP2930(tx) => tx.gas_limit,
+            Transaction::EIP1559(tx) => tx.gas_limit,
+        }
+    }
+
+    pub fn input(&self) -> &str {
+        match self {
+            Transaction::Legacy(tx) => &tx.input,
+            Transaction::EIP2930(tx) => &tx.input,
+            Transaction::EIP1559(tx) => &tx.input,
+        }
+    }
+
+    pub fn action(&self) -> TransactionAction {
+        match self {
+            Transaction::Legacy(tx) => tx.action,
+            Transaction::EIP2930(tx) => tx.action,
+            Transaction::EIP1559(tx) => tx.action,
+        }
+    }
+
+    /// Returns the number of blobs carried by the transaction (always zero for legacy and EIP-2930 transactions).
+    pub fn blob_count(&self) -> u64 {
+        match self {
+            Transaction::EIP1559(tx) => tx.blob_count,
+            _ => 0,
+        }
+    }
+
+    /// Returns the access list of the transaction (always empty for legacy transactions).
+    pub fn access_list(&self) -> AccessList {
+        match self {
+            Transaction::Legacy(_) => vec![],
+            Transaction::EIP2930(tx) => tx.access_list.clone(),
+            Transaction::EIP1559(tx) => tx.access_list.clone(),
+        }
+    }
+
+    /// Returns a copy of the transaction with the given access list (legacy transactions are left unchanged).
+    pub fn with_access_list(&self, access_list: AccessList) -> Self {
+        let mut transaction = self.clone();
+        match &mut transaction {
+            Transaction::Legacy(_) => {}
+            Transaction::EIP2930(tx) => tx.access_list = access_list,
+            Transaction::EIP1559(tx) => tx.access_list = access_list,
+        }
+        transaction
+    }
 }
 
 /// The main gas cost estimator, it can estimate Legacy, EIP-2930 and EIP-1559 transactions.
 #[derive(Clone)]
-pub struct Estimator<C: Collector + Clone> {
+pub struct Estimator<C: Collector + Clone, S: StateProvider> {
     gas_price_collector: Arc<C>,
+    simulator: Arc<Simulator<S>>,
+    staleness_policy: StalenessPolicy,
 }
 
-impl<C: Collector + Clone> Estimator<C> {
-    pub fn new(gas_price_collector: Arc<C>) -> Self {
+impl<C: Collector + Clone, S: StateProvider + 'static> Estimator<C, S> {
+    pub fn new(
+        gas_price_collector: Arc<C>,
+        simulator: Arc<Simulator<S>>,
+        staleness_policy: StalenessPolicy,
+    ) -> Self {
         Self {
             gas_price_collector,
+            simulator,
+            staleness_policy,
+        }
+    }
+
+    /// Returns the fee snapshot collected at the given point, or the current one if it is fresh enough to estimate with.
+    pub async fn fee_snapshot(
+        &self,
+        history_point: Option<HistoryPoint>,
+    ) -> Result<FeeSnapshot, Error> {
+        if let Some(point) = history_point {
+            return fee_snapshot_at(self.gas_price_collector.as_ref(), point).await;
+        }
+        if !self.is_ready().await {
+            return Err(Error::GasPriceNotReady);
+        }
+        let snapshot = self
+            .gas_price_collector
+            .fee_snapshot()
+            .await
+            .ok_or(Error::GasPriceNotReady)?;
+        self.staleness_policy.check(&snapshot)?;
+        Ok(snapshot)
+    }
+
+    /// Returns the fee snapshot the given transactions are priced with. It is required for EIP-1559 transactions
+    /// and historical estimates, otherwise the current one is returned (if any) to be reported next to the estimate.
+    async fn pricing_snapshot(
+        &self,
+        transactions: &[Transaction],
+        history_point: Option<HistoryPoint>,
+    ) -> Result<Option<FeeSnapshot>, Error> {
+        let required = history_point.is_some()
+            || transactions
+                .iter()
+                .any(|transaction| matches!(transaction, Transaction::EIP1559(_)));
+        if required {
+            Ok(Some(self.fee_snapshot(history_point).await?))
+        } else {
+            Ok(self.gas_price_collector.fee_snapshot().await)
+        }
+    }
+
+    /// Returns the block number and age of the given fee snapshot an estimate is based on.
+    fn fee_data_info(
+        &self,
+        snapshot: &FeeSnapshot,
+        history_point: Option<HistoryPoint>,
+    ) -> FeeDataInfo {
+        // historical fee data is old by definition
+        let warning =
+            (history_point.is_none() && self.staleness_policy.is_stale(snapshot)).then(|| {
+                format!(
+                    "Fee data is stale, maximum allowed age is {}s",
+                    self.staleness_policy.max_age.as_secs()
+                )
+            });
+
+        FeeDataInfo {
+            block_number: snapshot.block_number,
+            data_age_seconds: snapshot.age_seconds(),
+            warning,
+        }
+    }
+
+    /// Returns true if the current gas price can be used for estimation.
+    pub async fn is_ready(&self) -> bool {
+        self.gas_price_collector.is_ready().await
+    }
+
+    /// Estimates the cost of the given transaction in WEI with the gas price collected at the given point in the past,
+    /// or with the current gas price if not set. Returns the estimate with the fee data it is based on.
+    pub async fn estimate_at(
+        &self,
+        transaction: Transaction,
+        history_point: Option<HistoryPoint>,
+    ) -> Result<(U256, Option<FeeDataInfo>), Error> {
+        validation::validate(&transaction)?;
+        let snapshot = self
+            .pricing_snapshot(std::slice::from_ref(&transaction), history_point)
+            .await?;
+        if let (Some(_), Some(snapshot)) = (history_point, &snapshot) {
+            check_historical_gas_price(&transaction, snapshot)?;
+        }
+        let fee = estimate_with(transaction, snapshot.as_ref())?;
+
+        Ok((
+            fee,
+            snapshot.map(|snapshot| self.fee_data_info(&snapshot, history_point)),
+        ))
+    }
+
+    /// Executes the given transaction locally and reports whether it would succeed and the fee of the gas it used,
+    /// custom errors in the revert data are decoded with the given ABI (if any).
+    pub async fn simulate(
+        &self,
+        transaction: &Transaction,
+        include_trace: bool,
+        abi: Option<&JsonAbi>,
+    ) -> Result<SimulationOutcome, Error> {
+        validation::validate(transaction)?;
+        let snapshot = self
+            .pricing_snapshot(std::slice::from_ref(transaction), None)
+            .await?;
+        let gas_price = gas_price(transaction, snapshot.as_ref())?;
+        let result = self.simulator.simulate(transaction, include_trace).await?;
+        Ok(SimulationOutcome::new(result, gas_price, abi)?)
+    }
+
+    /// Estimates the cost of the given transactions executed in order on a shared state in WEI.
+    /// Returns the estimate with the fee data it is based on.
+    pub async fn estimate_bundle(
+        &self,
+        transactions: &[Transaction],
+        abi: Option<&JsonAbi>,
+    ) -> Result<(BundleEstimate, Option<FeeDataInfo>), Error> {
+        for transaction in transactions {
+            validation::validate(transaction)?;
+        }
+        let snapshot = self.pricing_snapshot(transactions, None).await?;
+        let gas_prices = transactions
+            .iter()
+            .map(|transaction| gas_price(transaction, snapshot.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let estimate = BundleEstimator::new(self.simulator.clone())
+            .estimate(transactions, &gas_prices, abi)
+            .await?;
+        Ok((
+            estimate,
+            snapshot.map(|snapshot| self.fee_data_info(&snapshot, None)),
+        ))
+    }
+
+    /// Checks whether the sender of the given transaction can afford its maximum cost,
+    /// returns `None` if the sender is not set.
+    pub async fn affordability(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Option<Affordability>, Error> {
+        let Some(sender) = transaction.sender() else {
+            return Ok(None);
+        };
+        let state_provider = self.simulator.state_provider();
+        let balance = async {
+            let environment = state_provider.environment().await?;
+            state_provider.balance(sender, environment.number).await
+        }
+        .await
+        .map_err(|error| simulator::Error::StateProvider(error.to_string()))?;
+        let blob_base_fee = if transaction.blob_count() == 0 {
+            U256::zero()
+        } else {
+            self.gas_price_collector
+                .fee_snapshot()
+                .await
+                .ok_or(Error::GasPriceNotReady)?
+                .blob_base_fee
+                .ok_or(Error::BlobBaseFeeUnknown)?
+        };
+        Ok(Some(Affordability::new(
+            balance,
+            transaction,
+            blob_base_fee,
+        )?))
+    }
+
+    /// Estimates the cost of the given intent as Legacy, EIP-2930 and EIP-1559 transaction with the current base fee.
+    /// Returns the comparison with the fee data it is based on.
+    pub async fn compare_transaction_types(
+        &self,
+        intent: TransactionIntent,
+    ) -> Result<(TransactionTypeComparison, FeeDataInfo), Error> {
+        let snapshot = self.fee_snapshot(None).await?;
+        // transaction fees are in GWEI, so the current base fee is rounded up to not underpay
+        let (base_fee_per_gas, remainder) =
+            snapshot.base_fee_per_gas.div_mod(U256::from(1_000_000_000));
+        let base_fee_per_gas = if remainder.is_zero() {
+            base_fee_per_gas
+        } else {
+            base_fee_per_gas + 1
+        };
+        let (legacy, eip2930, eip1559) = intent.transactions(base_fee_per_gas)?;
+        let estimate = |transaction: Transaction| {
+            validation::validate(&transaction)?;
+            estimate_with(transaction, Some(&snapshot))
+        };
+
+        Ok((
+            TransactionTypeComparison {
+                legacy: estimate(legacy)?,
+                eip2930: estimate(eip2930)?,
+                eip1559: estimate(eip1559)?,
+            },
+            self.fee_data_info(&snapshot, None),
+        ))
+    }
+
+    /// Generates the optimal access list for the given transaction and estimates its cost in WEI with and without it.
+    /// The transaction is executed on top of the given state snapshot if set, otherwise on the default state.
+    /// Returns the estimate with the fee data it is based on.
+    pub async fn create_access_list(
+        &self,
+        transaction: Transaction,
+        state_snapshot: Option<StateSnapshot>,
+    ) -> Result<(AccessListEstimate, Option<FeeDataInfo>), Error> {
+        validation::validate(&transaction)?;
+        let snapshot = self
+            .pricing_snapshot(std::slice::from_ref(&transaction), None)
+            .await?;
+        let gas_price = gas_price(&transaction, snapshot.as_ref())?;
+        let estimate = match state_snapshot {
+            Some(state_snapshot) => {
+                let state_provider = Arc::new(SnapshotStateProvider::new(state_snapshot));
+                access_list::AccessListEstimator::new(Arc::new(Simulator::new(state_provider)))
+                    .estimate(transaction, gas_price)
+                    .await?
+            }
+            None => {
+                access_list::AccessListEstimator::new(self.simulator.clone())
+                    .estimate(transaction, gas_price)
+                    .await?
+            }
+        };
+        Ok((
+            estimate,
+            snapshot.map(|snapshot| self.fee_data_info(&snapshot, None)),
+        ))
+    }
+}
+
+/// Returns the gas price the given transaction pays in WEI, EIP-1559 transactions are priced with the given snapshot.
+fn gas_price(transaction: &Transaction, snapshot: Option<&FeeSnapshot>) -> Result<U256, Error> {
+    match transaction {
+        Transaction::Legacy(tx) => legacy::LegacyTransactionEstimator.gas_price(tx),
+        Transaction::EIP2930(tx) => eip2930::EIP2930TransactionEstimator.gas_price(tx),
+        Transaction::EIP1559(tx) => {
+            eip1559::EIP1559TransactionEstimator::new(snapshot.ok_or(Error::GasPriceNotReady)?)
+                .gas_price(tx)
         }
     }
+}
+
+/// Estimates the cost of the given (validated) transaction in WEI,
+/// EIP-1559 transactions are priced with the given snapshot.
+fn estimate_with(transaction: Transaction, snapshot: Option<&FeeSnapshot>) -> Result<U256, Error> {
+    match transaction {
+        Transaction::Legacy(tx) => legacy::LegacyTransactionEstimator.estimate(tx),
+        Transaction::EIP2930(tx) => eip2930::EIP2930TransactionEstimator.estimate(tx),
+        Transaction::EIP1559(tx) => {
+            eip1559::EIP1559TransactionEstimator::new(snapshot.ok_or(Error::GasPriceNotReady)?)
+                .estimate(tx)
+        }
+    }
+}
+
+/// Checks that the gas price of the given legacy or EIP-2930 transaction covers the base fee of the given historical
+/// snapshot, otherwise the transaction could not have been included then.
+fn check_historical_gas_price(
+    transaction: &Transaction,
+    snapshot: &FeeSnapshot,
+) -> Result<(), Error> {
+    let gas_price = match transaction {
+        Transaction::Legacy(tx) => tx.gas_price,
+        Transaction::EIP2930(tx) => tx.gas_price,
+        // max fee / gas is already checked against the historical base fee
+        Transaction::EIP1559(_) => return Ok(()),
+    };
+    let gas_price = validation::gwei_to_wei("gas_price", gas_price)?;
+    let base_fee = snapshot.base_fee_or_gas_price();
+    if gas_price < base_fee {
+        return Err(Error::GasPriceTooLow {
+            current: gas_price,
+            base_fee,
+        });
+    }
+    Ok(())
+}
+
+/// Returns the fee snapshot collected at or right before the given point,
+/// fails if the point is after the latest snapshot or before the oldest recorded one.
+pub(crate) async fn fee_snapshot_at<C: Collector>(
+    collector: &C,
+    point: HistoryPoint,
+) -> Result<FeeSnapshot, Error> {
+    let latest = collector
+        .fee_snapshot()
+        .await
+        .ok_or(Error::NoGasPriceHistory(point))?;
+    if point.is_after(&latest) {
+        return Err(Error::HistoryPointOutOfRange(point));
+    }
+    collector
+        .fee_snapshot_at(point)
+        .await
+        .ok_or(Error::NoGasPriceHistory(point))
+}
+
+/// Returns an error if the given execution failed, custom errors in the revert data are decoded with the given ABI (if any).
+pub(crate) fn ensure_success(
+    result: SimulationResult,
+    abi: Option<&JsonAbi>,
+) -> Result<SimulationResult, Error> {
+    match result.exit_reason {
+        ExitReason::Succeed(_) => Ok(result),
+        ExitReason::Revert(_) => Err(Error::Reverted(RevertReason::decode(&result.output, abi))),
+        exit_reason => Err(Error::ExecutionFailed(exit_reason)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
 
-    /// Estimates the cost of the given transaction in WEI.
-    pub async fn estimate(&self, transaction: Transaction) -> Result<U256, Error> {
-        match transaction {
-            Transaction::Legacy(tx) => legacy::LegacyTransactionEstimator.estimate(tx),
-            Transaction::EIP2930(tx) => eip2930::EIP2930TransactionEstimator.estimate(tx),
-            Transaction::EIP1559(tx) => {
-                eip1559::EIP1559TransactionEstimator::new(self.gas_price_collector.clone())
-                    .estimate(tx)
-                    .await
+    use async_trait::async_trait;
+    use tokio_util::sync::CancellationToken;
+
+    use crate::{
+        fee_estimator::comparison::TransactionType,
+        gas_price_collector::static_fees::{StaticCollector, StaticFees},
+    };
+
+    use super::*;
+
+    /// Serves the given snapshots (in block order) as the fee history, the last one is the current one.
+    #[derive(Clone)]
+    struct HistoryCollector(Vec<FeeSnapshot>);
+
+    #[async_trait]
+    impl Collector for HistoryCollector {
+        type Error = ();
+
+        async fn start(&self, _cancel_token: CancellationToken) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn fee_snapshot(&self) -> Option<FeeSnapshot> {
+            self.0.last().cloned()
+        }
+
+        async fn is_ready(&self) -> bool {
+            true
+        }
+
+        async fn fee_snapshot_at(&self, point: HistoryPoint) -> Option<FeeSnapshot> {
+            self.0
+                .iter()
+                .rev()
+                .find(|snapshot| snapshot.is_at_or_before(point))
+                .cloned()
+        }
+    }
+
+    /// Blocks 100 (1 GWEI base fee) and 110 (10 GWEI base fee) produced an hour ago and now.
+    fn history_estimator() -> Estimator<HistoryCollector, SnapshotStateProvider> {
+        let now = crate::gas_price_collector::unix_timestamp();
+        let snapshot = |block_number: u64, block_timestamp: u64, base_fee_per_gas: u64| {
+            let mut snapshot = StaticFees {
+                block_number: Some(block_number.into()),
+                base_fee_per_gas: base_fee_per_gas.into(),
+                max_priority_fee_per_gas: U256::zero(),
+                gas_price: None,
+                blob_base_fee: None,
             }
+            .snapshot(U256::zero(), "test");
+            snapshot.block_timestamp = Some(block_timestamp.into());
+            snapshot
+        };
+        estimator_with(HistoryCollector(vec![
+            snapshot(100, now - 3_600, 1_000_000_000),
+            snapshot(110, now, 10_000_000_000),
+        ]))
+    }
+
+    fn legacy_transfer(gas_price: u64) -> Transaction {
+        Transaction::Legacy(LegacyTransaction {
+            gas_price: gas_price.into(),
+            gas_limit: U256::from(100_000),
+            input: String::new(),
+            action: TransactionAction::Call(H160::repeat_byte(0x01)),
+            from: None,
+            value: U256::zero(),
+            runtime_code_size: None,
+        })
+    }
+
+    fn eip1559_transfer() -> Transaction {
+        Transaction::EIP1559(EIP1559Transaction {
+            max_priority_fee_per_gas: U256::zero(),
+            max_fee_per_gas: U256::from(30),
+            gas_limit: U256::from(100_000),
+            input: String::new(),
+            action: TransactionAction::Call(H160::repeat_byte(0x01)),
+            from: None,
+            value: U256::zero(),
+            runtime_code_size: None,
+            access_list: vec![],
+            blob_count: 0,
+        })
+    }
+
+    fn estimator(base_fee_per_gas: u64) -> Estimator<StaticCollector, SnapshotStateProvider> {
+        estimator_with(StaticCollector::new(StaticFees {
+            block_number: None,
+            base_fee_per_gas: base_fee_per_gas.into(),
+            max_priority_fee_per_gas: U256::from(3_000_000_000u64),
+            gas_price: None,
+            blob_base_fee: None,
+        }))
+    }
+
+    fn estimator_with<C: Collector + Clone>(collector: C) -> Estimator<C, SnapshotStateProvider> {
+        let state_provider = SnapshotStateProvider::new(StateSnapshot::default());
+        Estimator::new(
+            Arc::new(collector),
+            Arc::new(Simulator::new(Arc::new(state_provider))),
+            StalenessPolicy {
+                max_age: Duration::from_secs(60),
+                allow_stale: false,
+            },
+        )
+    }
+
+    fn transfer_intent(max_priority_fee_per_gas: u64) -> TransactionIntent {
+        TransactionIntent {
+            to: Some(H160::repeat_byte(0x01)),
+            input: String::new(),
+            value: U256::zero(),
+            gas_limit: U256::from(100_000),
+            from: None,
+            max_priority_fee_per_gas: max_priority_fee_per_gas.into(),
+            access_list: vec![],
         }
     }
+
+    #[tokio::test]
+    async fn transaction_types_are_priced_the_same() {
+        let (comparison, _) = estimator(10_000_000_000)
+            .compare_transaction_types(transfer_intent(2))
+            .await
+            .unwrap();
+
+        // gas used * (10 GWEI base fee + 2 GWEI priority fee)
+        let (gas_used, remainder) = comparison.legacy.div_mod(U256::from(12_000_000_000u64));
+        assert!(!gas_used.is_zero());
+        assert!(remainder.is_zero());
+        assert_eq!(comparison.eip2930, comparison.legacy);
+        assert_eq!(comparison.eip1559, comparison.legacy);
+        assert_eq!(comparison.cheapest(), TransactionType::EIP1559);
+    }
+
+    #[tokio::test]
+    async fn base_fee_is_rounded_up_to_gwei_for_legacy_transactions() {
+        let (comparison, _) = estimator(10_500_000_000)
+            .compare_transaction_types(transfer_intent(0))
+            .await
+            .unwrap();
+
+        let gas_used = comparison.eip1559 / U256::from(10_500_000_000u64);
+        assert_eq!(comparison.legacy, gas_used * U256::from(11_000_000_000u64));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn simulated_fee_is_based_on_the_used_gas() {
+        let estimator = estimator(10_000_000_000);
+        let transaction = Transaction::EIP1559(EIP1559Transaction {
+            max_priority_fee_per_gas: U256::from(2),
+            max_fee_per_gas: U256::from(30),
+            gas_limit: U256::from(100_000),
+            input: String::new(),
+            action: TransactionAction::Call(H160::repeat_byte(0x01)),
+            from: Some(H160::repeat_byte(0xaa)),
+            value: U256::zero(),
+            runtime_code_size: None,
+            access_list: vec![],
+            blob_count: 0,
+        });
+
+        let outcome = estimator.simulate(&transaction, true, None).await.unwrap();
+
+        // plain transfer: intrinsic gas * (10 GWEI base fee + 2 GWEI priority fee)
+        assert!(!outcome.reverted);
+        assert_eq!(outcome.gas_used, U256::from(21_000));
+        assert_eq!(outcome.fee_wei, U256::from(21_000u64 * 12_000_000_000));
+    }
+
+    #[tokio::test]
+    async fn blob_fees_need_a_blob_base_fee() {
+        let transaction = Transaction::EIP1559(EIP1559Transaction {
+            max_priority_fee_per_gas: U256::from(2),
+            max_fee_per_gas: U256::from(30),
+            gas_limit: U256::from(21_000),
+            input: String::new(),
+            action: TransactionAction::Call(H160::repeat_byte(0x01)),
+            from: Some(H160::repeat_byte(0xaa)),
+            value: U256::zero(),
+            runtime_code_size: None,
+            access_list: vec![],
+            blob_count: 1,
+        });
+
+        let error = estimator(10_000_000_000)
+            .affordability(&transaction)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, Error::BlobBaseFeeUnknown));
+    }
+
+    #[tokio::test]
+    async fn historical_estimate_uses_the_base_fee_of_the_block() {
+        let estimator = history_estimator();
+
+        let (current, current_fee_data) = estimator
+            .estimate_at(eip1559_transfer(), None)
+            .await
+            .unwrap();
+        let (at_block, at_block_fee_data) = estimator
+            .estimate_at(
+                eip1559_transfer(),
+                Some(HistoryPoint::BlockNumber(105.into())),
+            )
+            .await
+            .unwrap();
+        let now = crate::gas_price_collector::unix_timestamp();
+        let (at_timestamp, at_timestamp_fee_data) = estimator
+            .estimate_at(
+                eip1559_transfer(),
+                Some(HistoryPoint::Timestamp((now - 60).into())),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(current, at_block * 10);
+        assert_eq!(at_timestamp, at_block);
+        // the reported fee data is the snapshot the estimate is priced with
+        assert_eq!(current_fee_data.unwrap().block_number, U256::from(110));
+        assert_eq!(at_block_fee_data.unwrap().block_number, U256::from(100));
+        assert_eq!(at_timestamp_fee_data.unwrap().block_number, U256::from(100));
+    }
+
+    #[tokio::test]
+    async fn historical_estimate_out_of_range() {
+        let estimator = history_estimator();
+        let now = crate::gas_price_collector::unix_timestamp();
+
+        for point in [
+            HistoryPoint::BlockNumber(111.into()),
+            HistoryPoint::Timestamp((now + 3_600).into()),
+        ] {
+            let error = estimator
+                .estimate_at(eip1559_transfer(), Some(point))
+                .await
+                .unwrap_err();
+            assert!(matches!(error, Error::HistoryPointOutOfRange(_)));
+        }
+        let error = estimator
+            .estimate_at(
+                eip1559_transfer(),
+                Some(HistoryPoint::BlockNumber(99.into())),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::NoGasPriceHistory(_)));
+    }
+
+    #[tokio::test]
+    async fn historical_legacy_estimate_checks_the_base_fee() {
+        let estimator = history_estimator();
+        let point = Some(HistoryPoint::BlockNumber(105.into()));
+
+        // 5 GWEI covers the 1 GWEI base fee of block 100, but not the current 10 GWEI
+        let (fee, fee_data) = estimator
+            .estimate_at(legacy_transfer(5), point)
+            .await
+            .unwrap();
+        let (current, _) = estimator
+            .estimate_at(legacy_transfer(5), None)
+            .await
+            .unwrap();
+        assert_eq!(fee, current);
+        assert_eq!(fee_data.unwrap().block_number, U256::from(100));
+        let error = estimator
+            .estimate_at(
+                legacy_transfer(5),
+                Some(HistoryPoint::BlockNumber(110.into())),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::GasPriceTooLow { .. }));
+    }
 }
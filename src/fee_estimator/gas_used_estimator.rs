@@ -1,14 +1,17 @@
 use evm_disassembler::Opcode;
 use evm_gasometer::Gasometer;
 use evm_runtime::{Config, ExitError};
-use hex::FromHexError;
 use log::info;
-use primitive_types::{H160, H256};
+use primitive_types::{H160, H256, U256};
 use thiserror::Error;
 
 use crate::fee_estimator::CONTRACT_CREATION_GAS;
 
-use super::{Transaction, BASE_GAS_COUNT};
+use super::{
+    bytecode, precompile,
+    validation::{self, ValidationError},
+    Transaction, BASE_GAS_COUNT,
+};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -16,8 +19,14 @@ pub enum Error {
     GasometerExit(ExitError),
     #[error("EVM disassembler error: {0}")]
     EvmDisassembler(#[from] eyre::Report),
-    #[error("Hex decode error: {0}")]
-    HexDecode(#[from] FromHexError),
+    #[error("{0}")]
+    Validation(#[from] ValidationError),
+    #[error("Precompile error: {0}")]
+    Precompile(#[from] precompile::Error),
+    #[error("Init code size {size} exceeds the limit of {limit} bytes (EIP-3860)")]
+    InitCodeSizeLimit { size: usize, limit: usize },
+    #[error("Contract code size {size} exceeds the limit of {limit} bytes (EIP-170)")]
+    ContractCodeSizeLimit { size: usize, limit: usize },
 }
 
 /// Estimates the gas used for any given transaction.
@@ -43,6 +52,11 @@ impl GasUsedEstimator {
             Transaction::EIP2930(tx) => tx.action,
             Transaction::EIP1559(tx) => tx.action,
         };
+        let transaction_runtime_code_size = match &transaction {
+            Transaction::Legacy(tx) => tx.runtime_code_size,
+            Transaction::EIP2930(tx) => tx.runtime_code_size,
+            Transaction::EIP1559(tx) => tx.runtime_code_size,
+        };
         let transaction_access_list = match &transaction {
             Transaction::Legacy(_) => {
                 vec![]
@@ -54,6 +68,7 @@ impl GasUsedEstimator {
         .map(|item| (item.address, item.storage_keys.clone()))
         .collect::<Vec<(H160, Vec<H256>)>>();
 
+        let tx_input = validation::decode_hex("input", &transaction_input)?;
         let mut gasometer = Gasometer::new(self.gas_limit, &self.config);
 
         // add the default base gas
@@ -62,16 +77,13 @@ impl GasUsedEstimator {
             .map_err(Error::GasometerExit)?;
 
         // add the contract creation gas cost if action is create and input is not empty
-        if matches!(transaction_action, ethereum::TransactionAction::Create)
-            && transaction_input.trim() != ""
+        if matches!(transaction_action, ethereum::TransactionAction::Create) && !tx_input.is_empty()
         {
             gasometer
                 .record_cost(CONTRACT_CREATION_GAS.as_u64())
                 .map_err(Error::GasometerExit)?;
         }
 
-        let tx_input = hex::decode(transaction_input.as_str())?;
-
         // add transaction cost
         let tx_cost = match transaction_action {
             ethereum::TransactionAction::Call(_) => {
@@ -86,15 +98,29 @@ impl GasUsedEstimator {
             .record_transaction(tx_cost)
             .map_err(Error::GasometerExit)?;
 
-        // add opcode costs if applicable
-        if !transaction_input.is_empty() {
-            let operations = evm_disassembler::disassemble_bytes(tx_input.clone())?;
-            for operation in operations {
-                let op_code = self.get_evm_runtime_opcode(operation.opcode);
-                if let Some(cost) = evm_gasometer::static_opcode_cost(op_code) {
-                    gasometer.record_cost(cost).map_err(Error::GasometerExit)?;
+        // add code deposit cost of the deployed runtime code
+        if matches!(transaction_action, ethereum::TransactionAction::Create) {
+            self.record_code_deposit(&mut gasometer, &tx_input, transaction_runtime_code_size)?;
+        }
+
+        match transaction_action {
+            // precompile input is not bytecode, the cost is calculated from the input per spec
+            ethereum::TransactionAction::Call(address) if precompile::is_precompile(&address) => {
+                gasometer
+                    .record_cost(precompile::cost(&address, &tx_input)?)
+                    .map_err(Error::GasometerExit)?;
+            }
+            // add opcode costs if applicable
+            _ if !tx_input.is_empty() => {
+                let operations = bytecode::executable_operations(&tx_input, transaction_action)?;
+                for operation in operations {
+                    let op_code = self.get_evm_runtime_opcode(operation.opcode);
+                    if let Some(cost) = evm_gasometer::static_opcode_cost(op_code) {
+                        gasometer.record_cost(cost).map_err(Error::GasometerExit)?;
+                    }
                 }
             }
+            _ => {}
         }
 
         info!("Gas used for transaction: {}", gasometer.total_used_gas());
@@ -102,6 +128,43 @@ impl GasUsedEstimator {
         Ok(gasometer.total_used_gas())
     }
 
+    /// Validates init code and runtime code size limits and records the code deposit cost.
+    /// Runtime code size is taken from the transaction if set, otherwise it is derived from the init code.
+    fn record_code_deposit(
+        &self,
+        gasometer: &mut Gasometer,
+        init_code: &[u8],
+        runtime_code_size: Option<U256>,
+    ) -> Result<(), Error> {
+        if let Some(limit) = self.config.max_initcode_size {
+            if init_code.len() > limit {
+                return Err(Error::InitCodeSizeLimit {
+                    size: init_code.len(),
+                    limit,
+                });
+            }
+        }
+
+        let runtime_code_size = match runtime_code_size {
+            Some(size) => size,
+            None => bytecode::runtime_code_size(init_code)?,
+        };
+        let runtime_code_size =
+            validation::to_u64("runtime_code_size", runtime_code_size)? as usize;
+        if let Some(limit) = self.config.create_contract_limit {
+            if runtime_code_size > limit {
+                return Err(Error::ContractCodeSizeLimit {
+                    size: runtime_code_size,
+                    limit,
+                });
+            }
+        }
+
+        gasometer
+            .record_deposit(runtime_code_size)
+            .map_err(Error::GasometerExit)
+    }
+
     fn get_evm_runtime_opcode(&self, op_code: evm_disassembler::Opcode) -> evm_runtime::Opcode {
         match op_code {
             Opcode::STOP => evm_runtime::Opcode::STOP,
@@ -256,3 +319,50 @@ impl GasUsedEstimator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ethereum::TransactionAction;
+
+    use super::*;
+    use crate::fee_estimator::LegacyTransaction;
+
+    fn create_transaction(input: String, runtime_code_size: Option<U256>) -> Transaction {
+        Transaction::Legacy(LegacyTransaction {
+            gas_price: U256::one(),
+            gas_limit: U256::from(30_000_000),
+            input,
+            action: TransactionAction::Create,
+            from: None,
+            value: U256::zero(),
+            runtime_code_size,
+        })
+    }
+
+    fn estimator() -> GasUsedEstimator {
+        GasUsedEstimator::new(Config::cancun(), 30_000_000)
+    }
+
+    #[test]
+    fn oversized_runtime_code_size_is_rejected() {
+        // PUSH32 0xff..ff DUP1 PUSH1 0x00 PUSH1 0x00 CODECOPY PUSH1 0x00 RETURN INVALID
+        let input = format!("0x7f{}8060006000396000f3fe", "ff".repeat(32));
+        let result = estimator().estimate(create_transaction(input, None));
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn explicit_runtime_code_size_above_limit_is_rejected() {
+        let result = estimator().estimate(create_transaction(
+            "0x600080f3fe".to_string(),
+            Some(U256::from(0x6001)),
+        ));
+        assert!(matches!(
+            result,
+            Err(Error::ContractCodeSizeLimit {
+                size: 0x6001,
+                limit: 0x6000
+            })
+        ));
+    }
+}
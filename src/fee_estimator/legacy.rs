@@ -1,17 +1,27 @@
 use evm_runtime::Config;
 use primitive_types::U256;
 
-use super::{gas_used_estimator::GasUsedEstimator, Error, LegacyTransaction, Transaction};
+use super::{
+    gas_used_estimator::GasUsedEstimator,
+    validation::{checked_mul, gwei_to_wei, to_u64},
+    Error, LegacyTransaction, Transaction,
+};
 
 pub struct LegacyTransactionEstimator;
 
 impl LegacyTransactionEstimator {
     pub fn estimate(&self, transaction: LegacyTransaction) -> Result<U256, Error> {
-        let estimator = GasUsedEstimator::new(Config::london(), transaction.gas_limit.as_u64());
-        let gas_price = transaction
-            .gas_price
-            .saturating_mul(U256::from(1_000_000_000));
+        let estimator = GasUsedEstimator::new(
+            Config::cancun(),
+            to_u64("gas_limit", transaction.gas_limit)?,
+        );
+        let gas_price = self.gas_price(&transaction)?;
         let gas_used = estimator.estimate(Transaction::Legacy(transaction))?;
-        Ok(gas_price.saturating_mul(gas_used.into()))
+        Ok(checked_mul("gas_price", gas_price, gas_used.into())?)
+    }
+
+    /// Returns the gas price of the given transaction in WEI.
+    pub fn gas_price(&self, transaction: &LegacyTransaction) -> Result<U256, Error> {
+        Ok(gwei_to_wei("gas_price", transaction.gas_price)?)
     }
 }
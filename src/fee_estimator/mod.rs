@@ -1,10 +1,20 @@
 use lazy_static::lazy_static;
 
+pub mod access_list;
+pub mod affordability;
+pub mod bundle;
+pub mod bytecode;
+pub mod comparison;
 pub mod eip1559;
 pub mod eip2930;
 mod estimator;
 pub mod gas_used_estimator;
 pub mod legacy;
+pub mod precompile;
+pub mod revert;
+pub mod simulation;
+pub mod staleness;
+pub mod validation;
 pub use estimator::*;
 
 lazy_static! {
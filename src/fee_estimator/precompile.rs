@@ -0,0 +1,388 @@
+use primitive_types::{H160, U256};
+use thiserror::Error;
+
+/// Highest supported precompile address (BLS12-381 map FP2 to G2, Prague).
+const LAST_PRECOMPILE_ADDRESS: u8 = 0x11;
+
+const ECRECOVER_GAS: u64 = 3_000;
+const SHA256_BASE_GAS: u64 = 60;
+const SHA256_WORD_GAS: u64 = 12;
+const RIPEMD160_BASE_GAS: u64 = 600;
+const RIPEMD160_WORD_GAS: u64 = 120;
+const IDENTITY_BASE_GAS: u64 = 15;
+const IDENTITY_WORD_GAS: u64 = 3;
+const MODEXP_MIN_GAS: u64 = 200;
+const BN254_ADD_GAS: u64 = 150;
+const BN254_MUL_GAS: u64 = 6_000;
+const BN254_PAIRING_BASE_GAS: u64 = 45_000;
+const BN254_PAIRING_PER_POINT_GAS: u64 = 34_000;
+const BN254_PAIRING_POINT_LENGTH: usize = 192;
+const BLAKE2F_INPUT_LENGTH: usize = 213;
+const BLAKE2F_ROUND_GAS: u64 = 1;
+const POINT_EVALUATION_GAS: u64 = 50_000;
+const POINT_EVALUATION_INPUT_LENGTH: usize = 192;
+const BLS12_G1ADD_GAS: u64 = 375;
+const BLS12_G1ADD_INPUT_LENGTH: usize = 256;
+const BLS12_G1MSM_MULTIPLICATION_GAS: u64 = 12_000;
+const BLS12_G1MSM_PAIR_LENGTH: usize = 160;
+const BLS12_G2ADD_GAS: u64 = 600;
+const BLS12_G2ADD_INPUT_LENGTH: usize = 512;
+const BLS12_G2MSM_MULTIPLICATION_GAS: u64 = 22_500;
+const BLS12_G2MSM_PAIR_LENGTH: usize = 288;
+const BLS12_PAIRING_BASE_GAS: u64 = 37_700;
+const BLS12_PAIRING_PER_PAIR_GAS: u64 = 32_600;
+const BLS12_PAIRING_PAIR_LENGTH: usize = 384;
+const BLS12_MAP_FP_TO_G1_GAS: u64 = 5_500;
+const BLS12_MAP_FP_TO_G1_INPUT_LENGTH: usize = 64;
+const BLS12_MAP_FP2_TO_G2_GAS: u64 = 23_800;
+const BLS12_MAP_FP2_TO_G2_INPUT_LENGTH: usize = 128;
+/// Divisor of the MSM discounts.
+const BLS12_MSM_MULTIPLIER: u64 = 1_000;
+
+/// G1 MSM discounts by the number of pairs (1 - 128) as specified in EIP-2537,
+/// the last one is used for more pairs.
+const BLS12_G1MSM_DISCOUNTS: [u64; 128] = [
+    1000, 949, 848, 797, 764, 750, 738, 728, 719, 712, 705, 698, 692, 687, 682, 677, 673, 669, 665,
+    661, 658, 654, 651, 648, 645, 642, 640, 637, 635, 632, 630, 627, 625, 623, 621, 619, 617, 615,
+    613, 611, 609, 608, 606, 604, 603, 601, 599, 598, 596, 595, 593, 592, 591, 589, 588, 586, 585,
+    584, 582, 581, 580, 579, 577, 576, 575, 574, 573, 572, 570, 569, 568, 567, 566, 565, 564, 563,
+    562, 561, 560, 559, 558, 557, 556, 555, 554, 553, 552, 551, 550, 549, 548, 547, 547, 546, 545,
+    544, 543, 542, 541, 540, 540, 539, 538, 537, 536, 536, 535, 534, 533, 532, 532, 531, 530, 529,
+    528, 528, 527, 526, 525, 525, 524, 523, 522, 522, 521, 520, 520, 519,
+];
+
+/// G2 MSM discounts by the number of pairs (1 - 128) as specified in EIP-2537,
+/// the last one is used for more pairs.
+const BLS12_G2MSM_DISCOUNTS: [u64; 128] = [
+    1000, 1000, 923, 884, 855, 832, 812, 796, 782, 770, 759, 749, 740, 732, 724, 717, 711, 704,
+    699, 693, 688, 683, 679, 674, 670, 666, 663, 659, 655, 652, 649, 646, 643, 640, 637, 634, 632,
+    629, 627, 624, 622, 620, 618, 615, 613, 611, 609, 607, 606, 604, 602, 600, 598, 597, 595, 593,
+    592, 590, 589, 587, 586, 584, 583, 582, 580, 579, 578, 576, 575, 574, 573, 571, 570, 569, 568,
+    567, 566, 565, 563, 562, 561, 560, 559, 558, 557, 556, 555, 554, 553, 552, 552, 551, 550, 549,
+    548, 547, 546, 545, 545, 544, 543, 542, 541, 541, 540, 539, 538, 537, 537, 536, 535, 535, 534,
+    533, 532, 532, 531, 530, 530, 529, 528, 528, 527, 526, 526, 525, 524, 524,
+];
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid input for precompile {address:?}: {reason}")]
+    InvalidInput { address: H160, reason: String },
+}
+
+/// Returns true if the given address is a supported precompiled contract (0x01 - 0x11).
+pub fn is_precompile(address: &H160) -> bool {
+    let bytes = address.as_bytes();
+    bytes[..19].iter().all(|byte| *byte == 0) && (1..=LAST_PRECOMPILE_ADDRESS).contains(&bytes[19])
+}
+
+/// Calculates the gas cost of calling the given precompiled contract with the given input.
+pub fn cost(address: &H160, input: &[u8]) -> Result<u64, Error> {
+    let words = (input.len() as u64).div_ceil(32);
+    let cost = match address.as_bytes()[19] {
+        // ecrecover
+        0x01 => ECRECOVER_GAS,
+        // SHA256
+        0x02 => SHA256_BASE_GAS + SHA256_WORD_GAS * words,
+        // RIPEMD160
+        0x03 => RIPEMD160_BASE_GAS + RIPEMD160_WORD_GAS * words,
+        // identity
+        0x04 => IDENTITY_BASE_GAS + IDENTITY_WORD_GAS * words,
+        // modexp
+        0x05 => modexp_cost(input),
+        // BN254 add
+        0x06 => BN254_ADD_GAS,
+        // BN254 mul
+        0x07 => BN254_MUL_GAS,
+        // BN254 pairing
+        0x08 => {
+            if !input.len().is_multiple_of(BN254_PAIRING_POINT_LENGTH) {
+                return Err(invalid_input(
+                    address,
+                    format!("input length must be a multiple of {BN254_PAIRING_POINT_LENGTH}"),
+                ));
+            }
+            let points = (input.len() / BN254_PAIRING_POINT_LENGTH) as u64;
+            BN254_PAIRING_BASE_GAS + BN254_PAIRING_PER_POINT_GAS * points
+        }
+        // blake2f
+        0x09 => {
+            if input.len() != BLAKE2F_INPUT_LENGTH {
+                return Err(invalid_input(
+                    address,
+                    format!("input length must be {BLAKE2F_INPUT_LENGTH}"),
+                ));
+            }
+            if input[BLAKE2F_INPUT_LENGTH - 1] > 1 {
+                return Err(invalid_input(address, "final block flag must be 0 or 1"));
+            }
+            let rounds = u32::from_be_bytes([input[0], input[1], input[2], input[3]]);
+            BLAKE2F_ROUND_GAS * rounds as u64
+        }
+        // KZG point evaluation
+        0x0a => {
+            if input.len() != POINT_EVALUATION_INPUT_LENGTH {
+                return Err(invalid_input(
+                    address,
+                    format!("input length must be {POINT_EVALUATION_INPUT_LENGTH}"),
+                ));
+            }
+            POINT_EVALUATION_GAS
+        }
+        // BLS12-381 G1 add
+        0x0b => {
+            expect_length(address, input, BLS12_G1ADD_INPUT_LENGTH)?;
+            BLS12_G1ADD_GAS
+        }
+        // BLS12-381 G1 MSM
+        0x0c => {
+            let pairs = pair_count(address, input, BLS12_G1MSM_PAIR_LENGTH)?;
+            msm_cost(
+                pairs,
+                BLS12_G1MSM_MULTIPLICATION_GAS,
+                &BLS12_G1MSM_DISCOUNTS,
+            )
+        }
+        // BLS12-381 G2 add
+        0x0d => {
+            expect_length(address, input, BLS12_G2ADD_INPUT_LENGTH)?;
+            BLS12_G2ADD_GAS
+        }
+        // BLS12-381 G2 MSM
+        0x0e => {
+            let pairs = pair_count(address, input, BLS12_G2MSM_PAIR_LENGTH)?;
+            msm_cost(
+                pairs,
+                BLS12_G2MSM_MULTIPLICATION_GAS,
+                &BLS12_G2MSM_DISCOUNTS,
+            )
+        }
+        // BLS12-381 pairing check
+        0x0f => {
+            let pairs = pair_count(address, input, BLS12_PAIRING_PAIR_LENGTH)?;
+            BLS12_PAIRING_BASE_GAS + BLS12_PAIRING_PER_PAIR_GAS * pairs
+        }
+        // BLS12-381 map FP to G1
+        0x10 => {
+            expect_length(address, input, BLS12_MAP_FP_TO_G1_INPUT_LENGTH)?;
+            BLS12_MAP_FP_TO_G1_GAS
+        }
+        // BLS12-381 map FP2 to G2
+        0x11 => {
+            expect_length(address, input, BLS12_MAP_FP2_TO_G2_INPUT_LENGTH)?;
+            BLS12_MAP_FP2_TO_G2_GAS
+        }
+        _ => return Err(invalid_input(address, "not a precompile")),
+    };
+
+    Ok(cost)
+}
+
+/// Calculates modexp cost as specified in EIP-2565.
+fn modexp_cost(input: &[u8]) -> u64 {
+    let base_length = read_word(input, 0);
+    let exponent_length = read_word(input, 32);
+    let modulus_length = read_word(input, 64);
+
+    // multiplication complexity
+    let words = base_length
+        .max(modulus_length)
+        .saturating_add(U256::from(7))
+        / 8;
+    let multiplication_complexity = words.saturating_mul(words);
+
+    // iteration count, based on the first 32 bytes of the exponent
+    let exponent_head = if base_length > U256::from(input.len()) {
+        U256::zero()
+    } else {
+        let exponent_head_length = exponent_length.min(U256::from(32)).as_usize();
+        let bytes = read_bytes(input, 96 + base_length.as_usize(), exponent_head_length);
+        U256::from_big_endian(&bytes)
+    };
+    let exponent_head_bits = exponent_head.bits() as u64;
+    let iteration_count = if exponent_length <= U256::from(32) {
+        U256::from(exponent_head_bits.saturating_sub(1))
+    } else {
+        (exponent_length - 32)
+            .saturating_mul(U256::from(8))
+            .saturating_add(U256::from(exponent_head_bits.saturating_sub(1)))
+    }
+    .max(U256::one());
+
+    let cost = multiplication_complexity.saturating_mul(iteration_count) / 3;
+    if cost > U256::from(u64::MAX) {
+        return u64::MAX;
+    }
+
+    cost.as_u64().max(MODEXP_MIN_GAS)
+}
+
+/// Calculates BLS12-381 multi-scalar multiplication cost as specified in EIP-2537.
+fn msm_cost(pairs: u64, multiplication_gas: u64, discounts: &[u64]) -> u64 {
+    let discount = discounts
+        .get(pairs as usize - 1)
+        .or(discounts.last())
+        .copied()
+        .unwrap_or(BLS12_MSM_MULTIPLIER);
+    pairs
+        .saturating_mul(multiplication_gas)
+        .saturating_mul(discount)
+        / BLS12_MSM_MULTIPLIER
+}
+
+/// Returns the number of pairs in the given input, that has to be a non-empty list of `pair_length` long pairs.
+fn pair_count(address: &H160, input: &[u8], pair_length: usize) -> Result<u64, Error> {
+    if input.is_empty() || !input.len().is_multiple_of(pair_length) {
+        return Err(invalid_input(
+            address,
+            format!("input length must be a non-zero multiple of {pair_length}"),
+        ));
+    }
+    Ok((input.len() / pair_length) as u64)
+}
+
+fn expect_length(address: &H160, input: &[u8], length: usize) -> Result<(), Error> {
+    if input.len() != length {
+        return Err(invalid_input(
+            address,
+            format!("input length must be {length}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Reads a 32 bytes word from the given offset, missing bytes are considered to be zero.
+fn read_word(input: &[u8], offset: usize) -> U256 {
+    U256::from_big_endian(&read_bytes(input, offset, 32))
+}
+
+/// Reads bytes from the given offset, missing bytes are considered to be zero.
+fn read_bytes(input: &[u8], offset: usize, length: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; length];
+    if offset < input.len() {
+        let available = (input.len() - offset).min(length);
+        bytes[..available].copy_from_slice(&input[offset..offset + available]);
+    }
+    bytes
+}
+
+fn invalid_input(address: &H160, reason: impl Into<String>) -> Error {
+    Error::InvalidInput {
+        address: *address,
+        reason: reason.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn precompile(address: u8) -> H160 {
+        H160::from_low_u64_be(address as u64)
+    }
+
+    /// Builds a modexp input with the given lengths followed by the base, exponent and modulus.
+    fn modexp_input(base: &[u8], exponent: &[u8], modulus: &[u8]) -> Vec<u8> {
+        let mut input = vec![];
+        for length in [base.len(), exponent.len(), modulus.len()] {
+            let mut word = [0u8; 32];
+            U256::from(length).to_big_endian(&mut word);
+            input.extend_from_slice(&word);
+        }
+        input.extend_from_slice(base);
+        input.extend_from_slice(exponent);
+        input.extend_from_slice(modulus);
+        input
+    }
+
+    #[test]
+    fn precompile_addresses() {
+        assert!(!is_precompile(&precompile(0x00)));
+        assert!(is_precompile(&precompile(0x01)));
+        assert!(is_precompile(&precompile(0x0a)));
+        assert!(is_precompile(&precompile(0x0b)));
+        assert!(is_precompile(&precompile(0x11)));
+        assert!(!is_precompile(&precompile(0x12)));
+        assert!(!is_precompile(&H160::from_low_u64_be(0x0100)));
+    }
+
+    #[test]
+    fn modexp_small_input_costs_the_minimum() {
+        let input = modexp_input(&[3], &[3], &[5]);
+        assert_eq!(cost(&precompile(0x05), &input).unwrap(), MODEXP_MIN_GAS);
+    }
+
+    #[test]
+    fn modexp_cost_eip2565() {
+        // 64 bytes base and modulus: 8 words, multiplication complexity 64
+        let base = [0xff; 64];
+        let modulus = [0xff; 64];
+
+        // 256 bits exponent: 255 iterations
+        let input = modexp_input(&base, &[0xff; 32], &modulus);
+        assert_eq!(cost(&precompile(0x05), &input).unwrap(), 64 * 255 / 3);
+
+        // 64 bytes exponent with head 1: (64 - 32) * 8 + 0 iterations
+        let mut exponent = [0u8; 64];
+        exponent[31] = 1;
+        let input = modexp_input(&base, &exponent, &modulus);
+        assert_eq!(cost(&precompile(0x05), &input).unwrap(), 64 * 256 / 3);
+
+        // zero exponent still counts as one iteration
+        let input = modexp_input(&[0xff; 256], &[0], &[0xff; 256]);
+        assert_eq!(cost(&precompile(0x05), &input).unwrap(), 32 * 32 / 3);
+    }
+
+    #[test]
+    fn modexp_huge_lengths_saturate() {
+        let mut input = vec![0xff; 96];
+        input.extend_from_slice(&[0xff; 32]);
+        assert_eq!(cost(&precompile(0x05), &input).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn bn254_cost() {
+        assert_eq!(cost(&precompile(0x06), &[0; 128]).unwrap(), 150);
+        assert_eq!(cost(&precompile(0x07), &[0; 96]).unwrap(), 6_000);
+        assert_eq!(cost(&precompile(0x08), &[]).unwrap(), 45_000);
+        assert_eq!(
+            cost(&precompile(0x08), &[0; 2 * BN254_PAIRING_POINT_LENGTH]).unwrap(),
+            45_000 + 2 * 34_000
+        );
+        assert!(cost(&precompile(0x08), &[0; 100]).is_err());
+    }
+
+    #[test]
+    fn bls12_cost() {
+        assert_eq!(cost(&precompile(0x0b), &[0; 256]).unwrap(), 375);
+        assert_eq!(cost(&precompile(0x0c), &[0; 160]).unwrap(), 12_000);
+        assert_eq!(
+            cost(&precompile(0x0c), &[0; 2 * 160]).unwrap(),
+            2 * 12_000 * 949 / 1000
+        );
+        // more than 128 pairs use the maximum discount
+        assert_eq!(
+            cost(&precompile(0x0c), &[0; 200 * 160]).unwrap(),
+            200 * 12_000 * 519 / 1000
+        );
+        assert_eq!(cost(&precompile(0x0d), &[0; 512]).unwrap(), 600);
+        assert_eq!(
+            cost(&precompile(0x0e), &[0; 3 * 288]).unwrap(),
+            3 * 22_500 * 923 / 1000
+        );
+        assert_eq!(
+            cost(&precompile(0x0f), &[0; 2 * 384]).unwrap(),
+            37_700 + 2 * 32_600
+        );
+        assert_eq!(cost(&precompile(0x10), &[0; 64]).unwrap(), 5_500);
+        assert_eq!(cost(&precompile(0x11), &[0; 128]).unwrap(), 23_800);
+    }
+
+    #[test]
+    fn bls12_invalid_input_lengths() {
+        assert!(cost(&precompile(0x0b), &[0; 255]).is_err());
+        assert!(cost(&precompile(0x0c), &[]).is_err());
+        assert!(cost(&precompile(0x0e), &[0; 160]).is_err());
+        assert!(cost(&precompile(0x0f), &[]).is_err());
+        assert!(cost(&precompile(0x11), &[0; 64]).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt::{self, Display};
+
+use alloy::{
+    dyn_abi::{DynSolValue, ErrorExt},
+    json_abi::JsonAbi,
+    sol_types::{Panic, Revert, SolError},
+};
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+/// Decoded revert data of a failed transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RevertReason {
+    /// `Error(string)` raised by `require` and `revert`
+    Error { message: String },
+    /// `Panic(uint256)` raised by failed assertions, arithmetic errors etc...
+    Panic { code: U256, description: String },
+    /// Custom error found in the supplied ABI
+    Custom {
+        signature: String,
+        arguments: Vec<String>,
+    },
+    /// Revert data that could not be decoded
+    Unknown { data: String },
+}
+
+impl RevertReason {
+    /// Decodes the given revert data, custom errors are decoded only if they are present in the given ABI.
+    pub fn decode(data: &[u8], abi: Option<&JsonAbi>) -> Self {
+        if let Ok(revert) = Revert::abi_decode(data, true) {
+            return Self::Error {
+                message: revert.reason,
+            };
+        }
+
+        if let Ok(panic) = Panic::abi_decode(data, true) {
+            return Self::Panic {
+                code: U256::from_big_endian(&panic.code.to_be_bytes::<32>()),
+                description: panic
+                    .kind()
+                    .map(|kind| kind.as_str().to_string())
+                    .unwrap_or_else(|| "unknown panic code".to_string()),
+            };
+        }
+
+        let custom = abi.zip(data.get(..4)).and_then(|(abi, selector)| {
+            abi.errors()
+                .filter(|error| error.selector().as_slice() == selector)
+                .find_map(|error| {
+                    let decoded = error.decode_error(data).ok()?;
+                    Some(Self::Custom {
+                        signature: error.signature(),
+                        arguments: decoded.body.iter().map(format_value).collect(),
+                    })
+                })
+        });
+
+        custom.unwrap_or_else(|| Self::Unknown {
+            data: format!("0x{}", hex::encode(data)),
+        })
+    }
+}
+
+impl Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::Error { message } => write!(f, "{message}"),
+            RevertReason::Panic { code, description } => {
+                write!(f, "panic {code:#x} ({description})")
+            }
+            RevertReason::Custom {
+                signature,
+                arguments,
+            } => write!(f, "{signature} [{}]", arguments.join(", ")),
+            RevertReason::Unknown { data } => write!(f, "unknown revert data {data}"),
+        }
+    }
+}
+
+/// Formats a decoded ABI value in a human-readable way.
+fn format_value(value: &DynSolValue) -> String {
+    match value {
+        DynSolValue::Bool(value) => value.to_string(),
+        DynSolValue::Int(value, _) => value.to_string(),
+        DynSolValue::Uint(value, _) => value.to_string(),
+        DynSolValue::Address(address) => address.to_checksum(None),
+        DynSolValue::FixedBytes(word, size) => format!("0x{}", hex::encode(&word[..*size])),
+        DynSolValue::Bytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        DynSolValue::String(value) => value.clone(),
+        DynSolValue::Array(values) | DynSolValue::FixedArray(values) => {
+            format!(
+                "[{}]",
+                values
+                    .iter()
+                    .map(format_value)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        DynSolValue::Tuple(values) => {
+            format!(
+                "({})",
+                values
+                    .iter()
+                    .map(format_value)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        value => format!("{value:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{primitives::Address, sol, sol_types::PanicKind};
+
+    use super::*;
+
+    sol! {
+        error InsufficientBalance(uint256 available, address account);
+    }
+
+    fn abi() -> JsonAbi {
+        serde_json::from_str(
+            r#"[{
+                "type": "error",
+                "name": "InsufficientBalance",
+                "inputs": [
+                    {"name": "available", "type": "uint256"},
+                    {"name": "account", "type": "address"}
+                ]
+            }]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn decodes_error_string() {
+        let data = Revert::from("insufficient balance").abi_encode();
+
+        let reason = RevertReason::decode(&data, None);
+        assert!(
+            matches!(&reason, RevertReason::Error { message } if message == "insufficient balance"),
+            "{reason:?}"
+        );
+    }
+
+    #[test]
+    fn decodes_panic() {
+        let data = Panic::from(PanicKind::UnderOverflow).abi_encode();
+        let reason = RevertReason::decode(&data, None);
+        assert!(
+            matches!(&reason, RevertReason::Panic { code, description }
+                if *code == U256::from(0x11) && description == PanicKind::UnderOverflow.as_str()),
+            "{reason:?}"
+        );
+        assert_eq!(
+            reason.to_string(),
+            format!("panic 0x11 ({})", PanicKind::UnderOverflow.as_str())
+        );
+
+        let data = Panic::from(0x99).abi_encode();
+        let reason = RevertReason::decode(&data, None);
+        assert!(
+            matches!(&reason, RevertReason::Panic { code, description }
+                if *code == U256::from(0x99) && description == "unknown panic code"),
+            "{reason:?}"
+        );
+    }
+
+    #[test]
+    fn decodes_custom_error_with_abi() {
+        let account = Address::repeat_byte(0xaa);
+        let data = InsufficientBalance {
+            available: alloy::primitives::U256::from(100),
+            account,
+        }
+        .abi_encode();
+
+        let reason = RevertReason::decode(&data, Some(&abi()));
+        assert!(
+            matches!(&reason, RevertReason::Custom { signature, arguments }
+                if signature == "InsufficientBalance(uint256,address)"
+                    && *arguments == vec!["100".to_string(), account.to_checksum(None)]),
+            "{reason:?}"
+        );
+
+        // not decoded without the ABI
+        assert!(matches!(
+            RevertReason::decode(&data, None),
+            RevertReason::Unknown { .. }
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_unknown() {
+        for data in [
+            vec![],
+            vec![0x08, 0xc3],
+            vec![0xde, 0xad, 0xbe, 0xef, 0x01],
+            // selector of Error(string) with truncated data
+            Revert::from("insufficient balance").abi_encode()[..40].to_vec(),
+        ] {
+            let reason = RevertReason::decode(&data, Some(&abi()));
+            assert!(
+                matches!(&reason, RevertReason::Unknown { data: hex } if *hex == format!("0x{}", hex::encode(&data))),
+                "{reason:?}"
+            );
+        }
+    }
+}
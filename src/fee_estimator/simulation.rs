@@ -0,0 +1,134 @@
+use alloy::json_abi::JsonAbi;
+use evm::ExitReason;
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::simulator::{tracer::StructLog, SimulationResult};
+
+use super::{
+    revert::RevertReason,
+    validation::{checked_mul, ValidationError},
+};
+
+/// Outcome of a transaction executed locally on the current state, reported next to the fee estimate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationOutcome {
+    pub reverted: bool,
+    /// Decoded revert data, set only if the transaction reverted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<RevertReason>,
+    /// Reason of the failure if the transaction failed without reverting (e.g. out of gas)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Gas used by the execution
+    pub gas_used: U256,
+    /// Fee paid for the used gas in WEI
+    pub fee_wei: U256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Vec<StructLog>>,
+}
+
+impl SimulationOutcome {
+    /// Creates the outcome of the given execution paid with the given gas price (in WEI),
+    /// custom errors in the revert data are decoded with the given ABI (if any).
+    pub fn new(
+        result: SimulationResult,
+        gas_price: U256,
+        abi: Option<&JsonAbi>,
+    ) -> Result<Self, ValidationError> {
+        let (reverted, revert_reason, error) = match result.exit_reason {
+            ExitReason::Succeed(_) => (false, None, None),
+            ExitReason::Revert(_) => (true, Some(RevertReason::decode(&result.output, abi)), None),
+            exit_reason => (false, None, Some(format!("{exit_reason:?}"))),
+        };
+        Ok(Self {
+            reverted,
+            revert_reason,
+            error,
+            gas_used: result.gas_used.into(),
+            fee_wei: checked_mul("gas_used", gas_price, result.gas_used.into())?,
+            trace: result.trace,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::sol_types::{Revert, SolError};
+    use evm::{ExitError, ExitRevert, ExitSucceed};
+    use primitive_types::H160;
+
+    use super::*;
+
+    fn result(exit_reason: ExitReason, output: Vec<u8>) -> SimulationResult {
+        SimulationResult {
+            exit_reason,
+            output,
+            gas_used: 30_000,
+            created_address: None,
+            accessed: Default::default(),
+            coinbase: H160::zero(),
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn succeeded() {
+        let outcome = SimulationOutcome::new(
+            result(ExitReason::Succeed(ExitSucceed::Stopped), vec![]),
+            U256::from(10),
+            None,
+        )
+        .unwrap();
+
+        assert!(!outcome.reverted);
+        assert!(outcome.revert_reason.is_none());
+        assert!(outcome.error.is_none());
+        assert_eq!(outcome.gas_used, U256::from(30_000));
+        assert_eq!(outcome.fee_wei, U256::from(300_000));
+    }
+
+    #[test]
+    fn reverted() {
+        let output = Revert::from("insufficient balance").abi_encode();
+        let outcome = SimulationOutcome::new(
+            result(ExitReason::Revert(ExitRevert::Reverted), output),
+            U256::from(10),
+            None,
+        )
+        .unwrap();
+
+        assert!(outcome.reverted);
+        assert!(matches!(
+            outcome.revert_reason,
+            Some(RevertReason::Error { message }) if message == "insufficient balance"
+        ));
+        assert_eq!(outcome.fee_wei, U256::from(300_000));
+    }
+
+    #[test]
+    fn failed() {
+        let outcome = SimulationOutcome::new(
+            result(ExitReason::Error(ExitError::OutOfGas), vec![]),
+            U256::from(10),
+            None,
+        )
+        .unwrap();
+
+        assert!(!outcome.reverted);
+        assert!(outcome.revert_reason.is_none());
+        assert_eq!(outcome.error.as_deref(), Some("Error(OutOfGas)"));
+    }
+
+    #[test]
+    fn fee_overflow_names_gas_used() {
+        let error = SimulationOutcome::new(
+            result(ExitReason::Succeed(ExitSucceed::Stopped), vec![]),
+            U256::MAX,
+            None,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.field, "gas_used");
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::gas_price_collector::FeeSnapshot;
+
+use super::Error;
+
+/// Default maximum age of the collected fee data (5 missed polls of the collector).
+pub const DEFAULT_MAX_FEE_DATA_AGE: Duration = Duration::from_secs(150);
+
+/// Defines how estimates are handled when the collected fee data is too old (e.g. the upstream is down).
+#[derive(Debug, Clone, Copy)]
+pub struct StalenessPolicy {
+    /// Maximum age of the fee data to be considered fresh
+    pub max_age: Duration,
+    /// Estimate with stale fee data and warn about it instead of returning an error
+    pub allow_stale: bool,
+}
+
+impl StalenessPolicy {
+    pub fn is_stale(&self, snapshot: &FeeSnapshot) -> bool {
+        snapshot.age_seconds() > self.max_age.as_secs()
+    }
+
+    /// Returns an error if the given snapshot is stale and stale fee data is not allowed.
+    pub fn check(&self, snapshot: &FeeSnapshot) -> Result<(), Error> {
+        if !self.allow_stale && self.is_stale(snapshot) {
+            return Err(Error::StaleFeeData {
+                age: snapshot.age_seconds(),
+                max_age: self.max_age.as_secs(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Freshness of the fee data an estimate is based on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeDataInfo {
+    /// Block where the fee data was collected at
+    pub block_number: U256,
+    /// Seconds elapsed since the fee data was collected
+    pub data_age_seconds: u64,
+    /// Set if the fee data is stale, but it is still allowed to be used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
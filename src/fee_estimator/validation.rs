@@ -0,0 +1,133 @@
+use std::fmt::{self, Display};
+
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::Transaction;
+
+/// Machine-readable reason of a validation error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationErrorReason {
+    /// Value is not a valid hex string
+    InvalidHex,
+    /// Value does not fit into 64 bits
+    TooLarge,
+    /// Value overflows when converted to WEI or multiplied by the gas limit
+    Overflow,
+    /// Max priority fee / gas is higher than max fee / gas
+    PriorityFeeExceedsMaxFee,
+    /// Field can't be set together with another field
+    MutuallyExclusive,
+    /// Field is required
+    Missing,
+}
+
+impl Display for ValidationErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationErrorReason::InvalidHex => write!(f, "invalid hex string"),
+            ValidationErrorReason::TooLarge => write!(f, "value does not fit into 64 bits"),
+            ValidationErrorReason::Overflow => write!(f, "arithmetic overflow"),
+            ValidationErrorReason::PriorityFeeExceedsMaxFee => {
+                write!(f, "max priority fee / gas is higher than max fee / gas")
+            }
+            ValidationErrorReason::MutuallyExclusive => {
+                write!(f, "can't be set together with another field")
+            }
+            ValidationErrorReason::Missing => write!(f, "required"),
+        }
+    }
+}
+
+/// Invalid field of a request.
+#[derive(Debug, Clone, Error, Serialize, Deserialize)]
+#[error("Invalid {field}: {reason}")]
+pub struct ValidationError {
+    pub field: String,
+    pub reason: ValidationErrorReason,
+}
+
+impl ValidationError {
+    pub fn new(field: &str, reason: ValidationErrorReason) -> Self {
+        Self {
+            field: field.to_string(),
+            reason,
+        }
+    }
+}
+
+/// Validates all the fields of the given transaction before estimation.
+pub fn validate(transaction: &Transaction) -> Result<(), ValidationError> {
+    let gas_limit = U256::from(to_u64("gas_limit", transaction.gas_limit())?);
+    decode_hex("input", transaction.input())?;
+
+    let runtime_code_size = match transaction {
+        Transaction::Legacy(tx) => tx.runtime_code_size,
+        Transaction::EIP2930(tx) => tx.runtime_code_size,
+        Transaction::EIP1559(tx) => tx.runtime_code_size,
+    };
+    if let Some(runtime_code_size) = runtime_code_size {
+        to_u64("runtime_code_size", runtime_code_size)?;
+    }
+
+    match transaction {
+        Transaction::Legacy(tx) => {
+            checked_mul(
+                "gas_price",
+                gwei_to_wei("gas_price", tx.gas_price)?,
+                gas_limit,
+            )?;
+        }
+        Transaction::EIP2930(tx) => {
+            checked_mul(
+                "gas_price",
+                gwei_to_wei("gas_price", tx.gas_price)?,
+                gas_limit,
+            )?;
+        }
+        Transaction::EIP1559(tx) => {
+            let max_fee_per_gas = gwei_to_wei("max_fee_per_gas", tx.max_fee_per_gas)?;
+            checked_mul("max_fee_per_gas", max_fee_per_gas, gas_limit)?;
+            gwei_to_wei("max_priority_fee_per_gas", tx.max_priority_fee_per_gas)?;
+            if tx.max_priority_fee_per_gas > tx.max_fee_per_gas {
+                return Err(ValidationError::new(
+                    "max_priority_fee_per_gas",
+                    ValidationErrorReason::PriorityFeeExceedsMaxFee,
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes the given hex string (with or without `0x` prefix).
+pub fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, ValidationError> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    hex::decode(value).map_err(|_| ValidationError::new(field, ValidationErrorReason::InvalidHex))
+}
+
+pub fn to_u64(field: &str, value: U256) -> Result<u64, ValidationError> {
+    u64::try_from(value).map_err(|_| ValidationError::new(field, ValidationErrorReason::TooLarge))
+}
+
+/// Converts the given GWEI value to WEI.
+pub fn gwei_to_wei(field: &str, value: U256) -> Result<U256, ValidationError> {
+    checked_mul(field, value, U256::from(1_000_000_000))
+}
+
+pub fn checked_mul(field: &str, a: U256, b: U256) -> Result<U256, ValidationError> {
+    a.checked_mul(b)
+        .ok_or_else(|| ValidationError::new(field, ValidationErrorReason::Overflow))
+}
+
+pub fn checked_add(field: &str, a: U256, b: U256) -> Result<U256, ValidationError> {
+    a.checked_add(b)
+        .ok_or_else(|| ValidationError::new(field, ValidationErrorReason::Overflow))
+}
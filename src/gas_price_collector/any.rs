@@ -0,0 +1,110 @@
+use std::convert::Infallible;
+
+use alloy::rpc::client::RpcClient;
+use async_trait::async_trait;
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    in_memory::{self, InMemoryCollector},
+    local_node::{self, LocalNodeCollector},
+    on_demand::{self, OnDemandCollector},
+    replay::{self, ReplayCollector},
+    static_fees::StaticCollector,
+    websocket::{self, WebSocketCollector},
+    Collector, FeeSnapshot, HistoryPoint,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("In-memory gas price collector error: {0}")]
+    InMemory(#[from] in_memory::Error),
+    #[error("WebSocket gas price collector error: {0}")]
+    WebSocket(#[from] websocket::Error),
+    #[error("Local node gas price collector error: {0}")]
+    LocalNode(#[from] local_node::Error),
+    #[error("On-demand gas price collector error: {0}")]
+    OnDemand(#[from] on_demand::Error),
+    #[error("Replay gas price collector error: {0}")]
+    Replay(#[from] replay::Error),
+}
+
+impl From<Infallible> for Error {
+    fn from(error: Infallible) -> Self {
+        match error {}
+    }
+}
+
+/// Gas price collector that is selected at startup (through CLI arguments).
+#[derive(Clone)]
+pub enum AnyCollector {
+    InMemory(InMemoryCollector),
+    WebSocket(WebSocketCollector),
+    LocalNode(LocalNodeCollector),
+    OnDemand(OnDemandCollector),
+    Static(StaticCollector),
+    Replay(ReplayCollector),
+}
+
+impl AnyCollector {
+    /// Returns the ethereum JSON RPC client, so other services can reuse the same connection.
+    /// Static and replay collectors don't have any.
+    pub fn eth_client(&self) -> Option<RpcClient> {
+        match self {
+            AnyCollector::InMemory(collector) => Some(collector.eth_client()),
+            AnyCollector::WebSocket(collector) => Some(collector.eth_client()),
+            AnyCollector::LocalNode(collector) => Some(collector.eth_client()),
+            AnyCollector::OnDemand(collector) => Some(collector.eth_client()),
+            AnyCollector::Static(_) | AnyCollector::Replay(_) => None,
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for AnyCollector {
+    type Error = Error;
+
+    async fn start(&self, cancel_token: CancellationToken) -> Result<(), Self::Error> {
+        match self {
+            AnyCollector::InMemory(collector) => Ok(collector.start(cancel_token).await?),
+            AnyCollector::WebSocket(collector) => Ok(collector.start(cancel_token).await?),
+            AnyCollector::LocalNode(collector) => Ok(collector.start(cancel_token).await?),
+            AnyCollector::OnDemand(collector) => Ok(collector.start(cancel_token).await?),
+            AnyCollector::Static(collector) => Ok(collector.start(cancel_token).await?),
+            AnyCollector::Replay(collector) => Ok(collector.start(cancel_token).await?),
+        }
+    }
+
+    async fn fee_snapshot(&self) -> Option<FeeSnapshot> {
+        match self {
+            AnyCollector::InMemory(collector) => collector.fee_snapshot().await,
+            AnyCollector::WebSocket(collector) => collector.fee_snapshot().await,
+            AnyCollector::LocalNode(collector) => collector.fee_snapshot().await,
+            AnyCollector::OnDemand(collector) => collector.fee_snapshot().await,
+            AnyCollector::Static(collector) => collector.fee_snapshot().await,
+            AnyCollector::Replay(collector) => collector.fee_snapshot().await,
+        }
+    }
+
+    async fn is_ready(&self) -> bool {
+        match self {
+            AnyCollector::InMemory(collector) => collector.is_ready().await,
+            AnyCollector::WebSocket(collector) => collector.is_ready().await,
+            AnyCollector::LocalNode(collector) => collector.is_ready().await,
+            AnyCollector::OnDemand(collector) => collector.is_ready().await,
+            AnyCollector::Static(collector) => collector.is_ready().await,
+            AnyCollector::Replay(collector) => collector.is_ready().await,
+        }
+    }
+
+    async fn fee_snapshot_at(&self, point: HistoryPoint) -> Option<FeeSnapshot> {
+        match self {
+            AnyCollector::InMemory(collector) => collector.fee_snapshot_at(point).await,
+            AnyCollector::WebSocket(collector) => collector.fee_snapshot_at(point).await,
+            AnyCollector::LocalNode(collector) => collector.fee_snapshot_at(point).await,
+            AnyCollector::OnDemand(collector) => collector.fee_snapshot_at(point).await,
+            AnyCollector::Static(collector) => collector.fee_snapshot_at(point).await,
+            AnyCollector::Replay(collector) => collector.fee_snapshot_at(point).await,
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use std::{
+    cmp::Ordering as CmpOrdering,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use alloy::{
+    rpc::client::{ClientBuilder, RpcClient},
+    transports::{RpcError, TransportErrorKind},
+};
+use log::{debug, warn};
+use primitive_types::U256;
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::task::JoinSet;
+use url::Position;
+
+use super::{unix_timestamp, FeeSnapshot};
+use crate::rpc_budget::{layer::RpcBudgetLayer, RpcBudget};
+
+const LOG_TARGET: &str = "gas_price_collector::endpoints";
+
+/// Responses with a gas price more than this many times higher or lower than the median are discarded.
+const OUTLIER_FACTOR: u64 = 2;
+/// Ratio of the gas limit to the gas target of a block (EIP-1559).
+const ELASTICITY_MULTIPLIER: u64 = 2;
+/// Bounds the change of the base fee between two blocks to 1/8 (EIP-1559).
+const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Ethereum client JSON-RPC transport error: {0}")]
+    RpcTransport(#[from] RpcError<TransportErrorKind>),
+    #[error("No Ethereum client JSON-RPC endpoint is configured")]
+    NoEndpoints,
+    #[error("None of the Ethereum client JSON-RPC endpoints returned usable fee data")]
+    NoUsableResponse,
+    #[error("Only {usable} Ethereum client JSON-RPC endpoints returned usable fee data, at least {required} are required")]
+    QuorumNotReached { usable: usize, required: usize },
+    #[error("Minimum quorum must be between 1 and the number of endpoints ({endpoints}), got {min_quorum}")]
+    InvalidMinQuorum { min_quorum: usize, endpoints: usize },
+}
+
+/// Defines how fee data is fetched when multiple endpoints are configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EndpointStrategy {
+    /// Use one endpoint at a time and switch to the next one on errors
+    Failover,
+    /// Query all the endpoints and take the median of the usable responses
+    Quorum,
+}
+
+/// Block header fields returned by `eth_getBlockByNumber` (or `newHeads` subscriptions) that are needed for the fee snapshot.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHeader {
+    pub number: U256,
+    #[serde(default)]
+    pub timestamp: Option<U256>,
+    #[serde(default)]
+    pub base_fee_per_gas: U256,
+    #[serde(default)]
+    pub gas_used: Option<U256>,
+    #[serde(default)]
+    pub gas_limit: Option<U256>,
+    /// Set since Cancun, the blob base fee is derived from it
+    #[serde(default)]
+    pub excess_blob_gas: Option<U256>,
+}
+
+impl BlockHeader {
+    /// Returns the base fee / gas of the next block as defined by EIP-1559,
+    /// `None` if the header doesn't have the needed fields (e.g. the chain doesn't support EIP-1559).
+    pub fn next_base_fee(&self) -> Option<U256> {
+        let gas_used = self.gas_used?;
+        let gas_target = self.gas_limit? / ELASTICITY_MULTIPLIER;
+        let base_fee = self.base_fee_per_gas;
+        if gas_target.is_zero() || base_fee.is_zero() {
+            return None;
+        }
+        let denominator = gas_target * BASE_FEE_MAX_CHANGE_DENOMINATOR;
+        Some(match gas_used.cmp(&gas_target) {
+            CmpOrdering::Equal => base_fee,
+            CmpOrdering::Greater => base_fee.saturating_add(
+                (base_fee.saturating_mul(gas_used - gas_target) / denominator).max(U256::one()),
+            ),
+            CmpOrdering::Less => {
+                base_fee - base_fee.saturating_mul(gas_target - gas_used) / denominator
+            }
+        })
+    }
+}
+
+/// A single ethereum JSON RPC endpoint.
+#[derive(Clone)]
+pub struct RpcEndpoint {
+    client: RpcClient,
+    /// Host and port of the endpoint (the full URL can contain API keys)
+    source: String,
+}
+
+impl RpcEndpoint {
+    /// Creates an HTTP endpoint, its requests are recorded in the given budget.
+    pub fn new(url: url::Url, budget: Arc<RpcBudget>) -> Self {
+        let source = url[Position::BeforeHost..Position::AfterPort].to_string();
+        let client = ClientBuilder::default()
+            .layer(RpcBudgetLayer::new(budget))
+            .http(url);
+        Self { client, source }
+    }
+
+    /// Creates an endpoint from an already connected client.
+    pub fn from_client(client: RpcClient, source: String) -> Self {
+        Self { client, source }
+    }
+
+    pub fn client(&self) -> RpcClient {
+        self.client.clone()
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Fetches the blob base fee / gas of the next block.
+    pub async fn blob_base_fee(&self) -> Result<U256, Error> {
+        Ok(self
+            .client
+            .request_noparams::<U256>("eth_blobBaseFee")
+            .await?)
+    }
+
+    /// Fetches the current fee data of the endpoint.
+    pub async fn fetch(&self) -> Result<FeeSnapshot, Error> {
+        self.fetch_with_header(None).await
+    }
+
+    /// Fetches the current fee data of the endpoint,
+    /// the latest block header is fetched only if it is not given (e.g. received from a subscription).
+    pub async fn fetch_with_header(
+        &self,
+        header: Option<BlockHeader>,
+    ) -> Result<FeeSnapshot, Error> {
+        let block = async {
+            match header {
+                Some(header) => Ok(header),
+                None => {
+                    self.client
+                        .request::<_, BlockHeader>("eth_getBlockByNumber", ("latest", false))
+                        .await
+                }
+            }
+        };
+        let (gas_price, block, max_priority_fee_per_gas, blob_base_fee) = tokio::join!(
+            self.client.request_noparams::<U256>("eth_gasPrice"),
+            block,
+            self.client
+                .request_noparams::<U256>("eth_maxPriorityFeePerGas"),
+            self.client.request_noparams::<U256>("eth_blobBaseFee"),
+        );
+        let gas_price = gas_price?;
+        let block = block?;
+        // not all the nodes support these, so they are optional
+        let max_priority_fee_per_gas = max_priority_fee_per_gas.unwrap_or_else(|error| {
+            debug!(target: LOG_TARGET, "Failed to get max priority fee / gas: {error:?}");
+            gas_price.saturating_sub(block.base_fee_per_gas)
+        });
+        let blob_base_fee = blob_base_fee
+            .inspect_err(
+                |error| debug!(target: LOG_TARGET, "Failed to get blob base fee: {error:?}"),
+            )
+            .ok();
+
+        Ok(FeeSnapshot {
+            block_number: block.number,
+            block_timestamp: block.timestamp,
+            fetched_at: unix_timestamp().into(),
+            gas_price,
+            base_fee_per_gas: block.base_fee_per_gas,
+            max_priority_fee_per_gas,
+            blob_base_fee,
+            source: self.source.clone(),
+        })
+    }
+}
+
+/// Ethereum JSON RPC endpoints that fee data is fetched from based on the configured strategy.
+pub struct RpcEndpoints {
+    endpoints: Vec<RpcEndpoint>,
+    strategy: EndpointStrategy,
+    /// Responses more than this many blocks behind the latest response are discarded in quorum mode
+    max_block_lag: u64,
+    /// Minimum number of usable responses in quorum mode
+    min_quorum: usize,
+    /// Index of the endpoint that is tried first in failover mode
+    active: AtomicUsize,
+    budget: Arc<RpcBudget>,
+}
+
+impl RpcEndpoints {
+    /// Creates the endpoints of the given URLs, quorum mode requires `min_quorum` usable responses
+    /// (a majority of the endpoints if not set).
+    pub fn new(
+        urls: &[url::Url],
+        strategy: EndpointStrategy,
+        max_block_lag: u64,
+        min_quorum: Option<usize>,
+        budget: Arc<RpcBudget>,
+    ) -> Result<Self, Error> {
+        if urls.is_empty() {
+            return Err(Error::NoEndpoints);
+        }
+        let min_quorum = min_quorum.unwrap_or(urls.len() / 2 + 1);
+        if min_quorum == 0 || min_quorum > urls.len() {
+            return Err(Error::InvalidMinQuorum {
+                min_quorum,
+                endpoints: urls.len(),
+            });
+        }
+        Ok(Self {
+            endpoints: urls
+                .iter()
+                .map(|url| RpcEndpoint::new(url.clone(), budget.clone()))
+                .collect(),
+            strategy,
+            max_block_lag,
+            min_quorum,
+            active: AtomicUsize::new(0),
+            budget,
+        })
+    }
+
+    /// Returns the budget the requests of the endpoints are recorded in.
+    pub fn budget(&self) -> Arc<RpcBudget> {
+        self.budget.clone()
+    }
+
+    /// Returns the client of the first endpoint, so other services can reuse the same connection.
+    pub fn primary_client(&self) -> RpcClient {
+        self.endpoints[0].client()
+    }
+
+    /// Fetches the current fee data.
+    pub async fn fetch(&self) -> Result<FeeSnapshot, Error> {
+        match self.strategy {
+            EndpointStrategy::Failover => self.fetch_failover().await,
+            EndpointStrategy::Quorum => self.fetch_quorum().await,
+        }
+    }
+
+    /// Tries the endpoints one by one starting from the last working one.
+    async fn fetch_failover(&self) -> Result<FeeSnapshot, Error> {
+        let active = self.active.load(Ordering::Relaxed);
+        let mut last_error = Error::NoEndpoints;
+        for offset in 0..self.endpoints.len() {
+            let index = (active + offset) % self.endpoints.len();
+            let endpoint = &self.endpoints[index];
+            match endpoint.fetch().await {
+                Ok(snapshot) => {
+                    if index != active {
+                        warn!(target: LOG_TARGET, "Failed over to endpoint {}", endpoint.source);
+                        self.active.store(index, Ordering::Relaxed);
+                    }
+                    return Ok(snapshot);
+                }
+                Err(error) => {
+                    warn!(target: LOG_TARGET, "Endpoint {} failed: {error:?}", endpoint.source);
+                    last_error = error;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// Queries all the endpoints and combines the usable responses by taking their median,
+    /// responses behind the latest block or far from the median gas price are discarded.
+    /// Fails if less than the minimum quorum of responses are usable.
+    async fn fetch_quorum(&self) -> Result<FeeSnapshot, Error> {
+        let mut requests = JoinSet::new();
+        for endpoint in self.endpoints.iter().cloned() {
+            requests.spawn(async move { (endpoint.source.clone(), endpoint.fetch().await) });
+        }
+        let mut snapshots = vec![];
+        while let Some(response) = requests.join_next().await {
+            match response {
+                Ok((_, Ok(snapshot))) => snapshots.push(snapshot),
+                Ok((source, Err(error))) => {
+                    warn!(target: LOG_TARGET, "Endpoint {source} failed: {error:?}")
+                }
+                Err(error) => warn!(target: LOG_TARGET, "Endpoint request panicked: {error:?}"),
+            }
+        }
+
+        let (latest_block, latest_block_timestamp) = snapshots
+            .iter()
+            .map(|snapshot| (snapshot.block_number, snapshot.block_timestamp))
+            .max()
+            .ok_or(Error::NoUsableResponse)?;
+        snapshots.retain(|snapshot| {
+            let lagging = snapshot.block_number.saturating_add(self.max_block_lag.into()) < latest_block;
+            if lagging {
+                warn!(target: LOG_TARGET, "Endpoint {} is stuck at block {}", snapshot.source, snapshot.block_number);
+            }
+            !lagging
+        });
+
+        let median_gas_price = median(snapshots.iter().map(|snapshot| snapshot.gas_price))
+            .ok_or(Error::NoUsableResponse)?;
+        snapshots.retain(|snapshot| {
+            let outlier = snapshot.gas_price > median_gas_price.saturating_mul(OUTLIER_FACTOR.into())
+                || snapshot.gas_price.saturating_mul(OUTLIER_FACTOR.into()) < median_gas_price;
+            if outlier {
+                warn!(target: LOG_TARGET, "Endpoint {} returned outlier gas price {}", snapshot.source, snapshot.gas_price);
+            }
+            !outlier
+        });
+        if snapshots.len() < self.min_quorum {
+            return Err(Error::QuorumNotReached {
+                usable: snapshots.len(),
+                required: self.min_quorum,
+            });
+        }
+
+        let mut sources: Vec<_> = snapshots
+            .iter()
+            .map(|snapshot| snapshot.source.clone())
+            .collect();
+        sources.sort();
+        Ok(FeeSnapshot {
+            block_number: latest_block,
+            block_timestamp: latest_block_timestamp,
+            fetched_at: unix_timestamp().into(),
+            gas_price: median(snapshots.iter().map(|snapshot| snapshot.gas_price))
+                .ok_or(Error::NoUsableResponse)?,
+            base_fee_per_gas: median(snapshots.iter().map(|snapshot| snapshot.base_fee_per_gas))
+                .unwrap_or_default(),
+            max_priority_fee_per_gas: median(
+                snapshots
+                    .iter()
+                    .map(|snapshot| snapshot.max_priority_fee_per_gas),
+            )
+            .unwrap_or_default(),
+            blob_base_fee: median(
+                snapshots
+                    .iter()
+                    .filter_map(|snapshot| snapshot.blob_base_fee),
+            ),
+            source: sources.join(","),
+        })
+    }
+}
+
+/// Returns the median of the given values (the lower one of the middle values for even count).
+fn median(values: impl Iterator<Item = U256>) -> Option<U256> {
+    let mut values: Vec<_> = values.collect();
+    values.sort();
+    values.get(values.len().saturating_sub(1) / 2).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::test_utils::StubRpcServer;
+
+    /// Answers fee data requests with the given block number and gas price (base fee is half of the gas price).
+    async fn endpoint(block_number: u64, gas_price: u64) -> StubRpcServer {
+        StubRpcServer::start(move |method: &str, _: &Value| match method {
+            "eth_gasPrice" => Some(json!(format!("{gas_price:#x}"))),
+            "eth_getBlockByNumber" => Some(json!({
+                "number": format!("{block_number:#x}"),
+                "timestamp": "0x67e2a1bb",
+                "baseFeePerGas": format!("{:#x}", gas_price / 2),
+            })),
+            "eth_maxPriorityFeePerGas" => Some(json!("0x1")),
+            "eth_blobBaseFee" => Some(json!("0x2")),
+            _ => None,
+        })
+        .await
+    }
+
+    async fn failing_endpoint() -> StubRpcServer {
+        StubRpcServer::start(|_: &str, _: &Value| None).await
+    }
+
+    fn endpoints(
+        servers: &[&StubRpcServer],
+        strategy: EndpointStrategy,
+        min_quorum: Option<usize>,
+    ) -> Result<RpcEndpoints, Error> {
+        let urls: Vec<_> = servers.iter().map(|server| server.url.clone()).collect();
+        RpcEndpoints::new(
+            &urls,
+            strategy,
+            2,
+            min_quorum,
+            Arc::new(RpcBudget::new(None)),
+        )
+    }
+
+    fn source(server: &StubRpcServer) -> String {
+        server.url[Position::BeforeHost..Position::AfterPort].to_string()
+    }
+
+    fn header(base_fee_per_gas: u64, gas_used: u64) -> BlockHeader {
+        BlockHeader {
+            number: U256::from(100),
+            timestamp: None,
+            base_fee_per_gas: base_fee_per_gas.into(),
+            gas_used: Some(gas_used.into()),
+            gas_limit: Some(U256::from(30_000_000)),
+            excess_blob_gas: None,
+        }
+    }
+
+    #[test]
+    fn next_base_fee_follows_the_gas_used() {
+        // full block: +12.5%, empty block: -12.5%, at the target: unchanged
+        assert_eq!(
+            header(1_000_000_000, 30_000_000).next_base_fee(),
+            Some(U256::from(1_125_000_000))
+        );
+        assert_eq!(
+            header(1_000_000_000, 0).next_base_fee(),
+            Some(U256::from(875_000_000))
+        );
+        assert_eq!(
+            header(1_000_000_000, 15_000_000).next_base_fee(),
+            Some(U256::from(1_000_000_000))
+        );
+        assert_eq!(
+            header(1_000_000_000, 22_500_000).next_base_fee(),
+            Some(U256::from(1_062_500_000))
+        );
+        // the base fee grows by at least 1 WEI above the target
+        assert_eq!(header(7, 15_000_001).next_base_fee(), Some(U256::from(8)));
+    }
+
+    #[test]
+    fn next_base_fee_needs_the_header_fields() {
+        let mut pre_london = header(0, 30_000_000);
+        assert_eq!(pre_london.next_base_fee(), None);
+        pre_london.base_fee_per_gas = U256::from(1_000_000_000);
+        pre_london.gas_used = None;
+        assert_eq!(pre_london.next_base_fee(), None);
+    }
+
+    #[tokio::test]
+    async fn failover_switches_to_the_next_endpoint() {
+        let failing = failing_endpoint().await;
+        let working = endpoint(100, 20).await;
+        let endpoints = endpoints(&[&failing, &working], EndpointStrategy::Failover, None).unwrap();
+
+        let snapshot = endpoints.fetch().await.unwrap();
+        assert_eq!(snapshot.source, source(&working));
+        assert_eq!(snapshot.gas_price, U256::from(20));
+
+        // the working endpoint is tried first from now on
+        let failed_requests = failing.requests().len();
+        endpoints.fetch().await.unwrap();
+        assert_eq!(failing.requests().len(), failed_requests);
+        assert_eq!(working.count("eth_gasPrice"), 2);
+    }
+
+    #[tokio::test]
+    async fn failover_fails_if_all_endpoints_fail() {
+        let first = failing_endpoint().await;
+        let second = failing_endpoint().await;
+        let endpoints = endpoints(&[&first, &second], EndpointStrategy::Failover, None).unwrap();
+
+        assert!(matches!(
+            endpoints.fetch().await,
+            Err(Error::RpcTransport(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn quorum_takes_the_median_of_usable_responses() {
+        let servers = [
+            endpoint(100, 20).await,
+            endpoint(100, 22).await,
+            endpoint(99, 24).await,
+            // stuck behind the latest block
+            endpoint(90, 26).await,
+            // outlier gas price
+            endpoint(100, 100).await,
+        ];
+        let endpoints = endpoints(
+            &servers.iter().collect::<Vec<_>>(),
+            EndpointStrategy::Quorum,
+            None,
+        )
+        .unwrap();
+
+        let snapshot = endpoints.fetch().await.unwrap();
+
+        assert_eq!(snapshot.block_number, U256::from(100));
+        assert_eq!(snapshot.gas_price, U256::from(22));
+        assert_eq!(snapshot.base_fee_per_gas, U256::from(11));
+        assert_eq!(snapshot.blob_base_fee, Some(U256::from(2)));
+        let mut sources: Vec<_> = servers[..3].iter().map(source).collect();
+        sources.sort();
+        assert_eq!(snapshot.source, sources.join(","));
+    }
+
+    #[tokio::test]
+    async fn quorum_needs_a_majority_by_default() {
+        let working = endpoint(100, 20).await;
+        let first_failing = failing_endpoint().await;
+        let second_failing = failing_endpoint().await;
+        let servers = [&working, &first_failing, &second_failing];
+
+        let error = endpoints(&servers, EndpointStrategy::Quorum, None)
+            .unwrap()
+            .fetch()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            Error::QuorumNotReached {
+                usable: 1,
+                required: 2
+            }
+        ));
+
+        let snapshot = endpoints(&servers, EndpointStrategy::Quorum, Some(1))
+            .unwrap()
+            .fetch()
+            .await
+            .unwrap();
+        assert_eq!(snapshot.source, source(&working));
+    }
+
+    #[tokio::test]
+    async fn quorum_discarded_responses_do_not_count() {
+        let first = endpoint(100, 20).await;
+        let lagging = endpoint(90, 20).await;
+        let servers = [&first, &lagging];
+
+        let error = endpoints(&servers, EndpointStrategy::Quorum, None)
+            .unwrap()
+            .fetch()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            Error::QuorumNotReached {
+                usable: 1,
+                required: 2
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn min_quorum_is_validated() {
+        let first = endpoint(100, 20).await;
+        let second = endpoint(100, 20).await;
+
+        for min_quorum in [0, 3] {
+            assert!(matches!(
+                endpoints(
+                    &[&first, &second],
+                    EndpointStrategy::Quorum,
+                    Some(min_quorum)
+                ),
+                Err(Error::InvalidMinQuorum { endpoints: 2, .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::{sync::Arc, time::Duration};
+
+use log::{error, info, warn};
+use tokio::sync::RwLock;
+
+use super::{
+    storage::{self, any::AnyGasPriceStorage, GasPriceStorage},
+    FeeSnapshot, HistoryPoint,
+};
+
+const LOG_TARGET: &str = "gas_price_collector::fee_state";
+
+/// The current fee snapshot.
+#[derive(Debug, Clone)]
+struct CurrentFees {
+    snapshot: FeeSnapshot,
+    /// True if the snapshot was loaded from the storage and no fresh snapshot was collected since
+    stale: bool,
+}
+
+/// Fee data shared by the collectors: the current fee snapshot is kept in memory,
+/// every collected snapshot is recorded in the given storage.
+pub struct FeeState {
+    current: RwLock<Option<CurrentFees>>,
+    storage: Arc<AnyGasPriceStorage>,
+    /// Maximum age of a stale gas price to be used for estimation
+    max_stale_age: Option<Duration>,
+}
+
+impl FeeState {
+    /// Creates a new state, the last stored gas price is used until a fresh one is collected
+    /// only if it is not older than `max_stale_age`.
+    pub fn new(storage: Arc<AnyGasPriceStorage>, max_stale_age: Option<Duration>) -> Self {
+        Self {
+            current: RwLock::new(None),
+            storage,
+            max_stale_age,
+        }
+    }
+
+    /// Loads the last stored fee snapshot as stale, so it can be used until a fresh one is collected.
+    pub async fn load_last(&self) -> Result<(), storage::any::Error> {
+        let Some(snapshot) = self.storage.latest().await? else {
+            return Ok(());
+        };
+        let mut current = self.current.write().await;
+        if current.is_none() {
+            info!(target: LOG_TARGET, "Loaded last stored gas price: {} wei", snapshot.gas_price);
+            *current = Some(CurrentFees {
+                snapshot,
+                stale: true,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Sets the given fresh snapshot as the current one and records it.
+    /// A failure to record it is only logged, the snapshot is still served.
+    pub async fn update(&self, snapshot: FeeSnapshot) {
+        info!(target: LOG_TARGET, "Current gas price: {} wei", snapshot.gas_price);
+        *self.current.write().await = Some(CurrentFees {
+            snapshot: snapshot.clone(),
+            stale: false,
+        });
+        if let Err(error) = self.storage.record(snapshot).await {
+            warn!(target: LOG_TARGET, "Failed to record gas price: {error}");
+        }
+    }
+
+    pub async fn snapshot(&self) -> Option<FeeSnapshot> {
+        self.current
+            .read()
+            .await
+            .as_ref()
+            .map(|current| current.snapshot.clone())
+    }
+
+    /// Returns true if the current snapshot is fresh or the stale one is not too old.
+    pub async fn is_ready(&self) -> bool {
+        match self.current.read().await.as_ref() {
+            Some(current) if current.stale => self
+                .max_stale_age
+                .is_some_and(|max_age| current.snapshot.age_seconds() <= max_age.as_secs()),
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Returns true if the current snapshot was collected (not loaded from the storage) less than `ttl` ago.
+    pub async fn is_fresh(&self, ttl: Duration) -> bool {
+        self.current
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|current| !current.stale && current.snapshot.age_seconds() < ttl.as_secs())
+    }
+
+    pub async fn snapshot_at(&self, point: HistoryPoint) -> Option<FeeSnapshot> {
+        match self.storage.at(point).await {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                error!(target: LOG_TARGET, "Failed to look up gas price history: {error:?}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitive_types::U256;
+
+    use super::*;
+    use crate::gas_price_collector::{storage::file::FileStorage, unix_timestamp};
+
+    fn snapshot(block_number: u64) -> FeeSnapshot {
+        FeeSnapshot {
+            block_number: block_number.into(),
+            block_timestamp: None,
+            fetched_at: unix_timestamp().into(),
+            gas_price: U256::from(block_number),
+            base_fee_per_gas: U256::zero(),
+            max_priority_fee_per_gas: U256::zero(),
+            blob_base_fee: None,
+            source: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_recording_keeps_the_snapshot() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("history.jsonl");
+        let storage = FileStorage::open(&path, 1).await.unwrap();
+        // the second sample compacts the file, which fails as the compacted file can't be written
+        std::fs::create_dir(path.with_extension("compacting")).unwrap();
+        let state = FeeState::new(Arc::new(AnyGasPriceStorage::File(storage.clone())), None);
+
+        state.update(snapshot(1)).await;
+        state.update(snapshot(2)).await;
+
+        assert!(state.is_ready().await);
+        assert_eq!(state.snapshot().await.unwrap().block_number, U256::from(2));
+        // the sample is still kept in memory for lookups
+        assert_eq!(
+            storage.latest().await.unwrap().unwrap().block_number,
+            U256::from(2)
+        );
+        assert!(storage.record(snapshot(3)).await.is_err());
+    }
+}
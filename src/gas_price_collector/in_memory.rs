@@ -1,50 +1,65 @@
 use std::{sync::Arc, time::Duration};
 
-use alloy::{
-    rpc::client::RpcClient,
-    transports::{RpcError, TransportErrorKind},
-};
+use alloy::rpc::client::RpcClient;
 use async_trait::async_trait;
-use log::{error, info};
+use log::error;
 use primitive_types::U256;
 use thiserror::Error;
-use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 
-use super::Collector;
+use super::{
+    endpoints::{self, RpcEndpoints},
+    fee_state::FeeState,
+    polling::{self, PollingConfig},
+    storage::any::AnyGasPriceStorage,
+    Collector, FeeSnapshot, HistoryPoint,
+};
 
 const LOG_TARGET: &str = "gas_price_collector::in_memory";
 
-/// In memory gas price collector that fetches gas price through an ethereum JSON RPC call.
-/// The current gas price is stored in memory only.
+/// In memory gas price collector that fetches fee data through ethereum JSON RPC calls from one or more endpoints.
+/// The current fee snapshot is stored in memory only, every collected snapshot is recorded in the given storage.
 #[derive(Clone)]
 pub struct InMemoryCollector {
-    eth_client: RpcClient,
-    gas_price: Arc<RwLock<U256>>,
+    endpoints: Arc<RpcEndpoints>,
+    state: Arc<FeeState>,
+    polling: PollingConfig,
 }
 
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("Ethereum client JSON-RPC transport error: {0}")]
-    RpcTransport(#[from] RpcError<TransportErrorKind>),
+    #[error("Ethereum client JSON-RPC error: {0}")]
+    Endpoints(#[from] endpoints::Error),
 }
 
 impl InMemoryCollector {
-    pub fn new(eth_rpc_client_url: url::Url) -> Self {
-        let eth_client = alloy::rpc::client::ClientBuilder::default().http(eth_rpc_client_url);
+    /// Creates a new collector, the last stored gas price is used until a fresh one is collected
+    /// only if it is not older than `max_stale_age`.
+    pub fn new(
+        endpoints: RpcEndpoints,
+        storage: Arc<AnyGasPriceStorage>,
+        max_stale_age: Option<Duration>,
+        polling: PollingConfig,
+    ) -> Self {
         Self {
-            eth_client,
-            gas_price: Arc::new(RwLock::new(U256::zero())),
+            endpoints: Arc::new(endpoints),
+            state: Arc::new(FeeState::new(storage, max_stale_age)),
+            polling,
         }
     }
 
-    pub async fn update_gas_price(&self) -> Result<(), Error> {
-        let current_gas_price_wei: U256 = self.eth_client.request_noparams("eth_gasPrice").await?;
-        let mut gas_price_lock = self.gas_price.write().await;
-        *gas_price_lock = current_gas_price_wei;
-        info!(target: LOG_TARGET, "Current gas price: {} wei", *gas_price_lock);
+    /// Returns the ethereum JSON RPC client of the primary endpoint, so other services can reuse the same connection.
+    pub fn eth_client(&self) -> RpcClient {
+        self.endpoints.primary_client()
+    }
 
-        Ok(())
+    /// Fetches and stores the current fee data, returns the block number it was collected at.
+    pub async fn update_gas_price(&self) -> Result<U256, Error> {
+        let snapshot = self.endpoints.fetch().await?;
+        let block_number = snapshot.block_number;
+        self.state.update(snapshot).await;
+
+        Ok(block_number)
     }
 }
 
@@ -53,25 +68,30 @@ impl Collector for InMemoryCollector {
     type Error = Error;
 
     async fn start(&self, cancel_token: CancellationToken) -> Result<(), Self::Error> {
-        let mut interval = tokio::time::interval(Duration::from_secs(30));
-        loop {
-            tokio::select! {
-                _ = interval.tick() => {
-                    if let Err(error) = self.update_gas_price().await {
-                        error!(target: LOG_TARGET, "Failed to update gas price: {error:?}");
-                    }
-                }
-                _ = cancel_token.cancelled() => {
-                    break;
-                }
-            }
+        if let Err(error) = self.state.load_last().await {
+            error!(target: LOG_TARGET, "Failed to load last gas price: {error:?}");
         }
 
+        polling::run(
+            self.polling,
+            &self.endpoints.budget(),
+            &cancel_token,
+            || self.update_gas_price(),
+        )
+        .await;
+
         Ok(())
     }
 
-    async fn gas_price(&self) -> U256 {
-        let gas_price_lock = self.gas_price.read().await;
-        *gas_price_lock
+    async fn fee_snapshot(&self) -> Option<FeeSnapshot> {
+        self.state.snapshot().await
+    }
+
+    async fn is_ready(&self) -> bool {
+        self.state.is_ready().await
+    }
+
+    async fn fee_snapshot_at(&self, point: HistoryPoint) -> Option<FeeSnapshot> {
+        self.state.snapshot_at(point).await
     }
 }
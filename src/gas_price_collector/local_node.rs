@@ -0,0 +1,326 @@
+use std::{
+    convert::Infallible,
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use alloy::{
+    pubsub::{PubSubConnect, PubSubFrontend},
+    rpc::{
+        client::{ClientBuilder, RpcClient},
+        json_rpc::{RequestPacket, ResponsePacket},
+    },
+    transports::{ipc::IpcConnect, RpcError, TransportError, TransportErrorKind, TransportFut},
+};
+use async_trait::async_trait;
+use log::{error, info, warn};
+use primitive_types::U256;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tower::Service;
+
+use super::{
+    endpoints::{self, RpcEndpoint},
+    fee_state::FeeState,
+    polling::{self, PollingConfig},
+    storage::any::AnyGasPriceStorage,
+    Collector, FeeSnapshot, HistoryPoint,
+};
+use crate::rpc_budget::{layer::RpcBudgetLayer, RpcBudget};
+
+const LOG_TARGET: &str = "gas_price_collector::local_node";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Local node JSON-RPC error: {0}")]
+    Endpoint(#[from] endpoints::Error),
+}
+
+/// Connection to a local ethereum node.
+#[derive(Debug, Clone)]
+pub enum LocalNode {
+    /// IPC unix socket path (e.g. `~/.ethereum/geth.ipc`)
+    Ipc(PathBuf),
+    /// Plain HTTP JSON RPC URL (e.g. `http://127.0.0.1:8545`)
+    Http(url::Url),
+}
+
+impl FromStr for LocalNode {
+    type Err = Infallible;
+
+    /// Parses HTTP(S) URLs as HTTP connection and anything else as IPC socket path.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match url::Url::parse(value) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(LocalNode::Http(url)),
+            _ => Ok(LocalNode::Ipc(PathBuf::from(value))),
+        }
+    }
+}
+
+/// Transport that connects to the IPC socket of the local node on the first request and reconnects on the next request
+/// once the connection is lost, so the node doesn't need to be running (or the socket to exist) when the service starts.
+#[derive(Clone)]
+struct ReconnectingIpc {
+    path: PathBuf,
+    connection: Arc<Mutex<Option<PubSubFrontend>>>,
+}
+
+impl ReconnectingIpc {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            connection: Default::default(),
+        }
+    }
+
+    /// Returns the current connection, connects if there is none.
+    async fn connection(&self) -> Result<PubSubFrontend, TransportError> {
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection.as_ref() {
+            return Ok(connection.clone());
+        }
+        let connected = IpcConnect::new(self.path.clone()).into_service().await?;
+        info!(target: LOG_TARGET, "Connected to local node {}", self.path.display());
+        *connection = Some(connected.clone());
+        Ok(connected)
+    }
+}
+
+impl Service<RequestPacket> for ReconnectingIpc {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let transport = self.clone();
+        Box::pin(async move {
+            let mut connection = transport.connection().await?;
+            let response = connection.call(request).await;
+            if matches!(
+                response,
+                Err(RpcError::Transport(TransportErrorKind::BackendGone))
+            ) {
+                warn!(target: LOG_TARGET, "Connection to local node {} is lost", transport.path.display());
+                transport.connection.lock().await.take();
+            }
+            response
+        })
+    }
+}
+
+/// Gas price collector that fetches fee data from a local ethereum node (geth, reth etc...)
+/// through its IPC unix socket or plain HTTP JSON RPC endpoint instead of a paid API.
+#[derive(Clone)]
+pub struct LocalNodeCollector {
+    endpoint: RpcEndpoint,
+    budget: Arc<RpcBudget>,
+    state: Arc<FeeState>,
+    polling: PollingConfig,
+}
+
+impl LocalNodeCollector {
+    /// Creates a collector of the given local node, its requests are recorded in the given budget.
+    /// The node is connected on the first request (and reconnected when the connection is lost).
+    /// The last stored gas price is used until a fresh one is collected only if it is not older than `max_stale_age`.
+    pub fn new(
+        node: &LocalNode,
+        budget: Arc<RpcBudget>,
+        storage: Arc<AnyGasPriceStorage>,
+        max_stale_age: Option<Duration>,
+        polling: PollingConfig,
+    ) -> Self {
+        let endpoint = match node {
+            LocalNode::Ipc(path) => {
+                let client = ClientBuilder::default()
+                    .layer(RpcBudgetLayer::new(budget.clone()))
+                    .transport(ReconnectingIpc::new(path.clone()), true);
+                RpcEndpoint::from_client(client, path.display().to_string())
+            }
+            LocalNode::Http(url) => RpcEndpoint::new(url.clone(), budget.clone()),
+        };
+        info!(target: LOG_TARGET, "Collecting fee data from local node {}", endpoint.source());
+
+        Self {
+            endpoint,
+            budget,
+            state: Arc::new(FeeState::new(storage, max_stale_age)),
+            polling,
+        }
+    }
+
+    /// Returns the ethereum JSON RPC client of the local node, so other services can reuse the same connection.
+    pub fn eth_client(&self) -> RpcClient {
+        self.endpoint.client()
+    }
+
+    /// Fetches and stores the current fee data, returns the block number it was collected at.
+    pub async fn update_gas_price(&self) -> Result<U256, Error> {
+        let snapshot = self.endpoint.fetch().await?;
+        let block_number = snapshot.block_number;
+        self.state.update(snapshot).await;
+
+        Ok(block_number)
+    }
+}
+
+#[async_trait]
+impl Collector for LocalNodeCollector {
+    type Error = Error;
+
+    async fn start(&self, cancel_token: CancellationToken) -> Result<(), Self::Error> {
+        if let Err(error) = self.state.load_last().await {
+            error!(target: LOG_TARGET, "Failed to load last gas price: {error:?}");
+        }
+
+        polling::run(self.polling, &self.budget, &cancel_token, || {
+            self.update_gas_price()
+        })
+        .await;
+
+        Ok(())
+    }
+
+    async fn fee_snapshot(&self) -> Option<FeeSnapshot> {
+        self.state.snapshot().await
+    }
+
+    async fn is_ready(&self) -> bool {
+        self.state.is_ready().await
+    }
+
+    async fn fee_snapshot_at(&self, point: HistoryPoint) -> Option<FeeSnapshot> {
+        self.state.snapshot_at(point).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        gas_price_collector::storage::ring_buffer::RingBufferStorage, test_utils::StubIpcServer,
+    };
+
+    fn node_responses(method: &str, _: &Value) -> Option<Value> {
+        match method {
+            "eth_gasPrice" => Some(json!("0x77359400")),
+            "eth_getBlockByNumber" => Some(json!({
+                "number": "0x14a7c3b",
+                "timestamp": "0x67e2a1bb",
+                "baseFeePerGas": "0x3b9aca00",
+            })),
+            "eth_maxPriorityFeePerGas" => Some(json!("0x5f5e100")),
+            "eth_blobBaseFee" => Some(json!("0x1")),
+            _ => None,
+        }
+    }
+
+    fn collector(path: PathBuf) -> LocalNodeCollector {
+        LocalNodeCollector::new(
+            &LocalNode::Ipc(path),
+            Arc::new(RpcBudget::new(None)),
+            Arc::new(AnyGasPriceStorage::RingBuffer(RingBufferStorage::new(10))),
+            None,
+            PollingConfig {
+                interval: Duration::from_secs(30),
+                min_interval: Duration::from_millis(50),
+                failure_threshold: 100,
+                cooldown: Duration::from_millis(200),
+            },
+        )
+    }
+
+    #[test]
+    fn parses_node() {
+        assert!(matches!(
+            "http://127.0.0.1:8545".parse(),
+            Ok(LocalNode::Http(_))
+        ));
+        assert!(matches!(
+            "/root/.ethereum/geth.ipc".parse(),
+            Ok(LocalNode::Ipc(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn fetches_fee_data_through_ipc() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("geth.ipc");
+        let node = StubIpcServer::start(&path, node_responses);
+        let collector = collector(path.clone());
+
+        collector.update_gas_price().await.unwrap();
+
+        let snapshot = collector.fee_snapshot().await.unwrap();
+        assert_eq!(snapshot.block_number, U256::from(0x14a7c3b));
+        assert_eq!(snapshot.block_timestamp, Some(U256::from(0x67e2a1bb)));
+        assert_eq!(snapshot.gas_price, U256::from(2_000_000_000));
+        assert_eq!(snapshot.base_fee_per_gas, U256::from(1_000_000_000));
+        assert_eq!(snapshot.max_priority_fee_per_gas, U256::from(100_000_000));
+        assert_eq!(snapshot.blob_base_fee, Some(U256::one()));
+        assert_eq!(snapshot.source, path.display().to_string());
+        assert_eq!(node.count("eth_gasPrice"), 1);
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_the_node_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("geth.ipc");
+        let node = StubIpcServer::start(&path, node_responses);
+        let collector = collector(path.clone());
+        collector.update_gas_price().await.unwrap();
+
+        drop(node);
+        std::fs::remove_file(&path).unwrap();
+        let restarted = StubIpcServer::start(&path, node_responses);
+        let reconnected = async {
+            while collector.update_gas_price().await.is_err() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), reconnected)
+            .await
+            .unwrap();
+
+        assert_eq!(restarted.count("eth_gasPrice"), 1);
+    }
+
+    #[tokio::test]
+    async fn connects_once_the_socket_exists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("geth.ipc");
+        // the node is not running yet, which must not prevent starting
+        let collector = collector(path.clone());
+        let cancel_token = CancellationToken::new();
+        let running = tokio::spawn({
+            let collector = collector.clone();
+            let cancel_token = cancel_token.clone();
+            async move { collector.start(cancel_token).await }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(collector.fee_snapshot().await.is_none());
+
+        let _node = StubIpcServer::start(&path, node_responses);
+        let collected = async {
+            while collector.fee_snapshot().await.is_none() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), collected)
+            .await
+            .unwrap();
+        cancel_token.cancel();
+        running.await.unwrap().unwrap();
+
+        assert!(collector.is_ready().await);
+    }
+}
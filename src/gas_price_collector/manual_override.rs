@@ -0,0 +1,220 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use log::info;
+use serde::Serialize;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+use super::{static_fees::StaticFees, unix_timestamp, Collector, FeeSnapshot, HistoryPoint};
+
+const LOG_TARGET: &str = "gas_price_collector::manual_override";
+
+const SOURCE: &str = "manual override";
+
+/// Fees pinned manually until the given time.
+#[derive(Debug, Clone, Serialize)]
+pub struct PinnedFees {
+    pub fees: StaticFees,
+    /// Unix timestamp in seconds when the override expires
+    pub expires_at: u64,
+}
+
+/// Wraps a gas price collector, so its fee data can be overridden manually for a period of time
+/// (e.g. to keep serving estimates during upstream incidents).
+/// The fee history is always served by the wrapped collector.
+#[derive(Clone)]
+pub struct OverridableCollector<C> {
+    inner: C,
+    pinned: Arc<RwLock<Option<PinnedFees>>>,
+}
+
+impl<C: Collector> OverridableCollector<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            pinned: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Serves the given fees instead of the collected ones for the given duration.
+    /// If the fees don't set a block number, the one of the current fee data is used.
+    pub async fn pin(&self, mut fees: StaticFees, duration: Duration) -> PinnedFees {
+        if fees.block_number.is_none() {
+            fees.block_number = self
+                .inner
+                .fee_snapshot()
+                .await
+                .map(|snapshot| snapshot.block_number);
+        }
+        let pinned = PinnedFees {
+            fees,
+            expires_at: unix_timestamp().saturating_add(duration.as_secs()),
+        };
+        info!(target: LOG_TARGET, "Fees pinned for {}s: {:?}", duration.as_secs(), pinned.fees);
+        *self.pinned.write().await = Some(pinned.clone());
+        pinned
+    }
+
+    /// Removes the override, returns the removed one if it was active.
+    pub async fn unpin(&self) -> Option<PinnedFees> {
+        let pinned = self
+            .pinned
+            .write()
+            .await
+            .take()
+            .filter(|pinned| pinned.expires_at > unix_timestamp());
+        if pinned.is_some() {
+            info!(target: LOG_TARGET, "Fee override removed");
+        }
+        pinned
+    }
+
+    /// Returns the active override.
+    pub async fn pinned(&self) -> Option<PinnedFees> {
+        self.pinned
+            .read()
+            .await
+            .as_ref()
+            .filter(|pinned| pinned.expires_at > unix_timestamp())
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl<C: Collector + Send + Sync> Collector for OverridableCollector<C> {
+    type Error = C::Error;
+
+    async fn start(&self, cancel_token: CancellationToken) -> Result<(), Self::Error> {
+        self.inner.start(cancel_token).await
+    }
+
+    async fn fee_snapshot(&self) -> Option<FeeSnapshot> {
+        match self.pinned().await {
+            Some(pinned) => Some(pinned.fees.snapshot(Default::default(), SOURCE)),
+            None => self.inner.fee_snapshot().await,
+        }
+    }
+
+    async fn is_ready(&self) -> bool {
+        self.pinned().await.is_some() || self.inner.is_ready().await
+    }
+
+    async fn fee_snapshot_at(&self, point: HistoryPoint) -> Option<FeeSnapshot> {
+        self.inner.fee_snapshot_at(point).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitive_types::U256;
+
+    use super::*;
+
+    /// Collector serving the given snapshot (as current and historical fee data), not ready without one.
+    #[derive(Clone)]
+    struct Upstream(Option<FeeSnapshot>);
+
+    #[async_trait]
+    impl Collector for Upstream {
+        type Error = ();
+
+        async fn start(&self, _cancel_token: CancellationToken) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn fee_snapshot(&self) -> Option<FeeSnapshot> {
+            self.0.clone()
+        }
+
+        async fn is_ready(&self) -> bool {
+            self.0.is_some()
+        }
+
+        async fn fee_snapshot_at(&self, _point: HistoryPoint) -> Option<FeeSnapshot> {
+            self.0.clone()
+        }
+    }
+
+    fn fees(base_fee_per_gas: u64) -> StaticFees {
+        StaticFees {
+            block_number: None,
+            base_fee_per_gas: base_fee_per_gas.into(),
+            max_priority_fee_per_gas: U256::one(),
+            gas_price: None,
+            blob_base_fee: None,
+        }
+    }
+
+    fn upstream(block_number: u64) -> Upstream {
+        Upstream(Some(fees(10).snapshot(block_number.into(), "upstream")))
+    }
+
+    #[tokio::test]
+    async fn pinned_fees_are_served_instead_of_collected_ones() {
+        let collector = OverridableCollector::new(upstream(100));
+
+        let pinned = collector.pin(fees(20), Duration::from_secs(60)).await;
+        assert_eq!(pinned.fees.block_number, Some(U256::from(100)));
+        assert!(pinned.expires_at >= unix_timestamp() + 59);
+
+        let snapshot = collector.fee_snapshot().await.unwrap();
+        assert_eq!(snapshot.source, SOURCE);
+        assert_eq!(snapshot.base_fee_per_gas, U256::from(20));
+        assert_eq!(snapshot.gas_price, U256::from(21));
+        assert_eq!(snapshot.block_number, U256::from(100));
+        // the history is not overridden
+        let historical = collector
+            .fee_snapshot_at(HistoryPoint::BlockNumber(100.into()))
+            .await
+            .unwrap();
+        assert_eq!(historical.source, "upstream");
+    }
+
+    #[tokio::test]
+    async fn unpin_restores_collected_fees() {
+        let collector = OverridableCollector::new(upstream(100));
+        collector.pin(fees(20), Duration::from_secs(60)).await;
+
+        let removed = collector.unpin().await.unwrap();
+        assert_eq!(removed.fees.base_fee_per_gas, U256::from(20));
+        assert!(collector.pinned().await.is_none());
+        assert_eq!(collector.fee_snapshot().await.unwrap().source, "upstream");
+        assert!(collector.unpin().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn expired_pin_is_not_served() {
+        let collector = OverridableCollector::new(upstream(100));
+        collector.pin(fees(20), Duration::ZERO).await;
+
+        assert!(collector.pinned().await.is_none());
+        assert_eq!(collector.fee_snapshot().await.unwrap().source, "upstream");
+        assert!(collector.unpin().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn pin_makes_the_collector_ready() {
+        let collector = OverridableCollector::new(Upstream(None));
+        assert!(!collector.is_ready().await);
+
+        let pinned = collector
+            .pin(
+                StaticFees {
+                    block_number: Some(U256::from(7)),
+                    ..fees(20)
+                },
+                Duration::from_secs(60),
+            )
+            .await;
+
+        assert!(collector.is_ready().await);
+        assert_eq!(pinned.fees.block_number, Some(U256::from(7)));
+        collector.unpin().await;
+        assert!(!collector.is_ready().await);
+    }
+}
@@ -1,8 +1,99 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use async_trait::async_trait;
 use primitive_types::U256;
+use serde::{Deserialize, Serialize};
 use tokio_util::sync::CancellationToken;
 
+pub mod any;
+pub mod endpoints;
+mod fee_state;
 pub mod in_memory;
+pub mod local_node;
+pub mod manual_override;
+pub mod on_demand;
+pub mod polling;
+pub mod replay;
+pub mod static_fees;
+pub mod storage;
+pub mod websocket;
+
+/// Point in time to look up the gas price at.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum HistoryPoint {
+    BlockNumber(U256),
+    /// Unix timestamp in seconds
+    Timestamp(U256),
+}
+
+impl HistoryPoint {
+    /// Returns true if the point is after the given latest snapshot (a later block, or a time in the future),
+    /// so no fee data can be collected for it yet.
+    pub fn is_after(&self, latest: &FeeSnapshot) -> bool {
+        match *self {
+            HistoryPoint::BlockNumber(block_number) => block_number > latest.block_number,
+            HistoryPoint::Timestamp(timestamp) => {
+                timestamp > latest.timestamp().max(unix_timestamp().into())
+            }
+        }
+    }
+}
+
+/// Fee conditions collected at a given block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeSnapshot {
+    pub block_number: U256,
+    /// Unix timestamp in seconds of the block, not known for manually set fees
+    #[serde(default)]
+    pub block_timestamp: Option<U256>,
+    /// Unix timestamp in seconds when the snapshot was fetched
+    #[serde(alias = "timestamp")]
+    pub fetched_at: U256,
+    /// Legacy gas price in WEI (base fee + suggested priority fee)
+    pub gas_price: U256,
+    /// Base fee / gas of the block in WEI
+    #[serde(default)]
+    pub base_fee_per_gas: U256,
+    /// Suggested priority fee / gas in WEI
+    #[serde(default)]
+    pub max_priority_fee_per_gas: U256,
+    /// Blob base fee / gas in WEI, not set if the chain doesn't support blobs
+    #[serde(default)]
+    pub blob_base_fee: Option<U256>,
+    /// Where the snapshot was fetched from
+    #[serde(default)]
+    pub source: String,
+}
+
+impl FeeSnapshot {
+    /// Returns the timestamp of the block, or the time the snapshot was fetched if it is not known.
+    pub fn timestamp(&self) -> U256 {
+        self.block_timestamp.unwrap_or(self.fetched_at)
+    }
+
+    /// Returns true if the snapshot's block is at or before the given point.
+    pub fn is_at_or_before(&self, point: HistoryPoint) -> bool {
+        match point {
+            HistoryPoint::BlockNumber(block_number) => self.block_number <= block_number,
+            HistoryPoint::Timestamp(timestamp) => self.timestamp() <= timestamp,
+        }
+    }
+
+    /// Returns the number of seconds elapsed since the snapshot was fetched.
+    pub fn age_seconds(&self) -> u64 {
+        let fetched_at = u64::try_from(self.fetched_at).unwrap_or_default();
+        unix_timestamp().saturating_sub(fetched_at)
+    }
+
+    /// Returns the base fee / gas, or the legacy gas price if the base fee is unknown (e.g. pre-London chains).
+    pub fn base_fee_or_gas_price(&self) -> U256 {
+        if self.base_fee_per_gas.is_zero() {
+            self.gas_price
+        } else {
+            self.base_fee_per_gas
+        }
+    }
+}
 
 /// The trait that all gas price collector must implement.
 /// It gives the chance to let the implementation handle how it gets the current gas price (through API or from an Ethereum node etc...).
@@ -13,6 +104,69 @@ pub trait Collector {
     /// Starts the collector.
     async fn start(&self, cancel_token: CancellationToken) -> Result<(), Self::Error>;
 
-    /// Returns actual gas price in WEI.
-    async fn gas_price(&self) -> U256;
+    /// Returns the latest fee snapshot, `None` if nothing is collected yet.
+    async fn fee_snapshot(&self) -> Option<FeeSnapshot>;
+
+    /// Returns true if the gas price can be used for estimation (it is fresh or not too old).
+    async fn is_ready(&self) -> bool;
+
+    /// Returns the last fee snapshot collected at or before the given point (if there is any).
+    async fn fee_snapshot_at(&self, point: HistoryPoint) -> Option<FeeSnapshot>;
+}
+
+/// Returns the current unix timestamp in seconds.
+pub(crate) fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(block_number: u64, block_timestamp: Option<u64>, fetched_at: u64) -> FeeSnapshot {
+        FeeSnapshot {
+            block_number: block_number.into(),
+            block_timestamp: block_timestamp.map(Into::into),
+            fetched_at: fetched_at.into(),
+            gas_price: U256::zero(),
+            base_fee_per_gas: U256::zero(),
+            max_priority_fee_per_gas: U256::zero(),
+            blob_base_fee: None,
+            source: String::new(),
+        }
+    }
+
+    #[test]
+    fn timestamp_lookups_use_the_block_timestamp() {
+        // fetched 30s after the block was produced
+        let snapshot = snapshot(100, Some(1_000), 1_030);
+
+        assert!(snapshot.is_at_or_before(HistoryPoint::Timestamp(1_000.into())));
+        assert!(!snapshot.is_at_or_before(HistoryPoint::Timestamp(999.into())));
+        assert!(snapshot.is_at_or_before(HistoryPoint::BlockNumber(100.into())));
+        assert!(!snapshot.is_at_or_before(HistoryPoint::BlockNumber(99.into())));
+    }
+
+    #[test]
+    fn fetch_time_is_used_without_block_timestamp() {
+        let snapshot = snapshot(100, None, 1_030);
+
+        assert!(snapshot.is_at_or_before(HistoryPoint::Timestamp(1_030.into())));
+        assert!(!snapshot.is_at_or_before(HistoryPoint::Timestamp(1_029.into())));
+    }
+
+    #[test]
+    fn points_after_the_latest_snapshot() {
+        let now = unix_timestamp();
+        let latest = snapshot(100, Some(now - 20), now - 10);
+
+        assert!(!HistoryPoint::BlockNumber(100.into()).is_after(&latest));
+        assert!(HistoryPoint::BlockNumber(101.into()).is_after(&latest));
+        // any time until now is covered by the latest snapshot
+        assert!(!HistoryPoint::Timestamp(now.into()).is_after(&latest));
+        assert!(HistoryPoint::Timestamp((now + 3_600).into()).is_after(&latest));
+    }
 }
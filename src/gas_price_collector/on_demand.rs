@@ -0,0 +1,256 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use alloy::rpc::client::RpcClient;
+use async_trait::async_trait;
+use log::{debug, error};
+use primitive_types::U256;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    endpoints::{self, RpcEndpoints},
+    fee_state::FeeState,
+    polling::{PollScheduler, PollingConfig},
+    storage::any::AnyGasPriceStorage,
+    Collector, FeeSnapshot, HistoryPoint,
+};
+
+const LOG_TARGET: &str = "gas_price_collector::on_demand";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Ethereum client JSON-RPC error: {0}")]
+    Endpoints(#[from] endpoints::Error),
+}
+
+/// The last fetch of fee data.
+struct LastFetch {
+    finished_at: Instant,
+    /// Number of JSON RPC requests the fetch needed
+    requests: u64,
+    /// Set after a failed fetch, no fetch is done before it
+    retry_at: Option<Instant>,
+}
+
+/// Fetches done so far, failed fetches are retried with the same backoff as polling.
+struct Fetches {
+    last: Option<LastFetch>,
+    scheduler: PollScheduler,
+}
+
+/// Gas price collector that fetches fee data only when an estimate needs it and the current one is older than a TTL,
+/// so nothing is fetched while there is no traffic. Concurrent requests wait for the same fetch.
+#[derive(Clone)]
+pub struct OnDemandCollector {
+    endpoints: Arc<RpcEndpoints>,
+    state: Arc<FeeState>,
+    ttl: Duration,
+    /// Locked while fetching, so concurrent requests are coalesced into a single fetch
+    fetches: Arc<Mutex<Fetches>>,
+}
+
+impl OnDemandCollector {
+    /// Creates a new collector, the last stored gas price is used until a fresh one is collected
+    /// only if it is not older than `max_stale_age`. Failed fetches are retried with the backoff of the given polling config.
+    pub fn new(
+        endpoints: RpcEndpoints,
+        storage: Arc<AnyGasPriceStorage>,
+        max_stale_age: Option<Duration>,
+        ttl: Duration,
+        polling: PollingConfig,
+    ) -> Self {
+        Self {
+            endpoints: Arc::new(endpoints),
+            state: Arc::new(FeeState::new(storage, max_stale_age)),
+            ttl,
+            fetches: Arc::new(Mutex::new(Fetches {
+                last: None,
+                scheduler: PollScheduler::new(polling),
+            })),
+        }
+    }
+
+    /// Returns the ethereum JSON RPC client of the primary endpoint, so other services can reuse the same connection.
+    pub fn eth_client(&self) -> RpcClient {
+        self.endpoints.primary_client()
+    }
+
+    /// Fetches and stores the current fee data, returns the block number it was collected at.
+    pub async fn update_gas_price(&self) -> Result<U256, Error> {
+        let snapshot = self.endpoints.fetch().await?;
+        let block_number = snapshot.block_number;
+        self.state.update(snapshot).await;
+
+        Ok(block_number)
+    }
+
+    /// Fetches the fee data if the current one is expired, unless another request fetched it in the meantime,
+    /// the last fetch failed too recently or fetching again would exceed the JSON RPC budget.
+    async fn refresh(&self) {
+        let requested_at = Instant::now();
+        if self.state.is_fresh(self.ttl).await {
+            return;
+        }
+
+        let mut fetches = self.fetches.lock().await;
+        let budget = self.endpoints.budget();
+        if let Some(last_fetch) = fetches.last.as_ref() {
+            // fetched by a concurrent request while waiting for the lock
+            if last_fetch.finished_at >= requested_at {
+                return;
+            }
+            if last_fetch
+                .retry_at
+                .is_some_and(|retry_at| Instant::now() < retry_at)
+            {
+                debug!(target: LOG_TARGET, "Fetch skipped, backing off after failures");
+                return;
+            }
+            if last_fetch.finished_at.elapsed() < budget.pacing_delay(last_fetch.requests) {
+                debug!(target: LOG_TARGET, "Fetch skipped to stay within the JSON RPC budget");
+                return;
+            }
+        }
+
+        let requests_before = budget.requests();
+        let retry_delay = match self.update_gas_price().await {
+            Ok(block_number) => {
+                fetches.scheduler.on_success(block_number);
+                None
+            }
+            Err(error) => {
+                error!(target: LOG_TARGET, "Failed to update gas price: {error:?}");
+                Some(fetches.scheduler.on_failure())
+            }
+        };
+        let finished_at = Instant::now();
+        fetches.last = Some(LastFetch {
+            finished_at,
+            requests: budget.requests().saturating_sub(requests_before),
+            retry_at: retry_delay.map(|retry_delay| finished_at + retry_delay),
+        });
+    }
+}
+
+#[async_trait]
+impl Collector for OnDemandCollector {
+    type Error = Error;
+
+    /// Loads the last stored gas price, fee data is fetched only on demand.
+    async fn start(&self, cancel_token: CancellationToken) -> Result<(), Self::Error> {
+        if let Err(error) = self.state.load_last().await {
+            error!(target: LOG_TARGET, "Failed to load last gas price: {error:?}");
+        }
+        cancel_token.cancelled().await;
+
+        Ok(())
+    }
+
+    async fn fee_snapshot(&self) -> Option<FeeSnapshot> {
+        self.refresh().await;
+        self.state.snapshot().await
+    }
+
+    /// Fetches the fee data only if the current one can't be used,
+    /// so readiness probes don't keep an idle collector fetching.
+    async fn is_ready(&self) -> bool {
+        if !self.state.is_ready().await {
+            self.refresh().await;
+        }
+        self.state.is_ready().await
+    }
+
+    async fn fee_snapshot_at(&self, point: HistoryPoint) -> Option<FeeSnapshot> {
+        self.state.snapshot_at(point).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        gas_price_collector::{
+            endpoints::EndpointStrategy, storage::ring_buffer::RingBufferStorage,
+        },
+        rpc_budget::RpcBudget,
+        test_utils::StubRpcServer,
+    };
+
+    async fn node(available: bool) -> StubRpcServer {
+        StubRpcServer::start(move |method: &str, _: &Value| match method {
+            _ if !available => None,
+            "eth_gasPrice" => Some(json!("0x77359400")),
+            "eth_getBlockByNumber" => Some(json!({
+                "number": "0x64",
+                "baseFeePerGas": "0x3b9aca00",
+            })),
+            _ => None,
+        })
+        .await
+    }
+
+    fn collector(node: &StubRpcServer) -> OnDemandCollector {
+        let endpoints = RpcEndpoints::new(
+            std::slice::from_ref(&node.url),
+            EndpointStrategy::Failover,
+            2,
+            None,
+            Arc::new(RpcBudget::new(None)),
+        )
+        .unwrap();
+        OnDemandCollector::new(
+            endpoints,
+            Arc::new(AnyGasPriceStorage::RingBuffer(RingBufferStorage::new(10))),
+            None,
+            Duration::from_secs(60),
+            PollingConfig {
+                interval: Duration::from_secs(30),
+                min_interval: Duration::from_millis(100),
+                failure_threshold: 5,
+                cooldown: Duration::from_secs(60),
+            },
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_requests_share_a_single_fetch() {
+        let node = node(true).await;
+        let collector = collector(&node);
+
+        let mut requests = tokio::task::JoinSet::new();
+        for _ in 0..20 {
+            let collector = collector.clone();
+            requests.spawn(async move { collector.fee_snapshot().await });
+        }
+        while let Some(snapshot) = requests.join_next().await {
+            assert_eq!(snapshot.unwrap().unwrap().block_number, U256::from(100));
+        }
+
+        assert_eq!(node.count("eth_gasPrice"), 1);
+        // fresh within the TTL
+        collector.fee_snapshot().await.unwrap();
+        assert_eq!(node.count("eth_gasPrice"), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_fetches_are_backed_off() {
+        let node = node(false).await;
+        let collector = collector(&node);
+
+        assert!(collector.fee_snapshot().await.is_none());
+        assert!(collector.fee_snapshot().await.is_none());
+        assert!(!collector.is_ready().await);
+        assert_eq!(node.count("eth_gasPrice"), 1);
+
+        // the first retry is at most 2 * 100ms later
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(collector.fee_snapshot().await.is_none());
+        assert_eq!(node.count("eth_gasPrice"), 2);
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    fmt::Debug,
+    future::Future,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use log::{debug, error, info, warn};
+use primitive_types::U256;
+use tokio_util::sync::CancellationToken;
+
+use crate::rpc_budget::RpcBudget;
+
+const LOG_TARGET: &str = "gas_price_collector::polling";
+
+/// Weight of the latest observation in the block time average.
+const BLOCK_TIME_WEIGHT: f64 = 0.25;
+
+/// Polling intervals, backoff and circuit breaker settings.
+#[derive(Debug, Clone, Copy)]
+pub struct PollingConfig {
+    /// Maximum interval between two polls, the interval is shortened to the observed block time
+    pub interval: Duration,
+    /// Minimum interval between two polls
+    pub min_interval: Duration,
+    /// Number of consecutive failures after which polling is suspended (circuit is opened)
+    pub failure_threshold: u32,
+    /// Duration of suspending polling after too many failures, also the maximum backoff
+    pub cooldown: Duration,
+}
+
+/// Decides when to poll next based on the observed block time and the failures.
+pub struct PollScheduler {
+    config: PollingConfig,
+    /// Average time between two blocks
+    block_time: Option<Duration>,
+    /// Last observed block number and when it was observed
+    last_block: Option<(U256, Instant)>,
+    consecutive_failures: u32,
+}
+
+impl PollScheduler {
+    pub fn new(config: PollingConfig) -> Self {
+        Self {
+            config,
+            block_time: None,
+            last_block: None,
+            consecutive_failures: 0,
+        }
+    }
+
+    /// Records a successful poll that returned the given block and returns the delay of the next poll.
+    /// The delay follows the observed block time, if no new block was produced since the last poll
+    /// the next one is done sooner to not miss it.
+    pub fn on_success(&mut self, block_number: U256) -> Duration {
+        self.on_success_at(block_number, Instant::now())
+    }
+
+    /// Records a successful poll that returned the given block at the given time.
+    fn on_success_at(&mut self, block_number: U256, now: Instant) -> Duration {
+        if self.consecutive_failures >= self.config.failure_threshold {
+            info!(target: LOG_TARGET, "Polling recovered, circuit closed");
+        }
+        self.consecutive_failures = 0;
+
+        let advanced = match self.last_block {
+            Some((last_block_number, observed_at)) if block_number > last_block_number => {
+                let blocks = (block_number - last_block_number).low_u64().max(1) as f64;
+                let observed = now.duration_since(observed_at).as_secs_f64() / blocks;
+                let block_time = match self.block_time {
+                    Some(block_time) => {
+                        block_time.as_secs_f64() * (1.0 - BLOCK_TIME_WEIGHT)
+                            + observed * BLOCK_TIME_WEIGHT
+                    }
+                    None => observed,
+                };
+                self.block_time = Some(Duration::from_secs_f64(block_time));
+                true
+            }
+            Some(_) => false,
+            None => true,
+        };
+        if advanced {
+            self.last_block = Some((block_number, now));
+        }
+
+        let interval = self.block_time.map_or(self.config.interval, |block_time| {
+            block_time.min(self.config.interval)
+        });
+        let delay = if advanced { interval } else { interval / 2 };
+        delay.max(self.config.min_interval)
+    }
+
+    /// Records a failed poll and returns the delay of the next poll.
+    /// The delay grows exponentially (with jitter) until the failure threshold is reached,
+    /// then polling is suspended for the cooldown period.
+    pub fn on_failure(&mut self) -> Duration {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.consecutive_failures == self.config.failure_threshold {
+            warn!(target: LOG_TARGET, "{} consecutive failures, circuit opened for {}s", self.consecutive_failures, self.config.cooldown.as_secs());
+        }
+        if self.consecutive_failures >= self.config.failure_threshold {
+            return self.config.cooldown;
+        }
+
+        let backoff = self
+            .config
+            .min_interval
+            .saturating_mul(2u32.saturating_pow(self.consecutive_failures))
+            .min(self.config.cooldown);
+        with_jitter(backoff)
+    }
+}
+
+/// Calls `update` repeatedly until cancelled, scheduled by a [`PollScheduler`]
+/// and slowed down to stay within the JSON RPC budget.
+/// `update` returns the block number of the collected fee data.
+pub async fn run<F, Fut, E>(
+    config: PollingConfig,
+    budget: &RpcBudget,
+    cancel_token: &CancellationToken,
+    mut update: F,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<U256, E>>,
+    E: Debug,
+{
+    let mut scheduler = PollScheduler::new(config);
+    let mut delay = Duration::ZERO;
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {
+                let requests_before = budget.requests();
+                let scheduled = match update().await {
+                    Ok(block_number) => scheduler.on_success(block_number),
+                    Err(error) => {
+                        error!(target: LOG_TARGET, "Failed to update gas price: {error:?}");
+                        scheduler.on_failure()
+                    }
+                };
+                let paced = budget.pacing_delay(budget.requests().saturating_sub(requests_before));
+                if paced > scheduled {
+                    debug!(target: LOG_TARGET, "Polling slowed down to stay within the JSON RPC budget");
+                }
+                delay = scheduled.max(paced);
+                debug!(target: LOG_TARGET, "Next poll in {}ms", delay.as_millis());
+            }
+            _ = cancel_token.cancelled() => {
+                break;
+            }
+        }
+    }
+}
+
+/// Returns a random duration between the half and the full given duration,
+/// so multiple instances don't retry at the same time.
+fn with_jitter(duration: Duration) -> Duration {
+    // sub-second part of the clock is random enough to spread retries
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let factor = 0.5 + f64::from(nanos % 1_000) / 2_000.0;
+    duration.mul_f64(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler(failure_threshold: u32, cooldown: u64) -> PollScheduler {
+        PollScheduler::new(PollingConfig {
+            interval: Duration::from_secs(30),
+            min_interval: Duration::from_secs(2),
+            failure_threshold,
+            cooldown: Duration::from_secs(cooldown),
+        })
+    }
+
+    fn assert_jittered(delay: Duration, backoff: u64) {
+        let backoff = Duration::from_secs(backoff);
+        assert!(
+            delay >= backoff / 2 && delay <= backoff,
+            "{delay:?} is not within the jitter of {backoff:?}"
+        );
+    }
+
+    #[test]
+    fn delay_follows_the_average_block_time() {
+        let mut scheduler = scheduler(5, 300);
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+
+        // no block time observed yet
+        assert_eq!(
+            scheduler.on_success_at(100.into(), at(0)),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            scheduler.on_success_at(101.into(), at(12)),
+            Duration::from_secs(12)
+        );
+        // 12 * 0.75 + 20 * 0.25
+        assert_eq!(
+            scheduler.on_success_at(102.into(), at(32)),
+            Duration::from_secs(14)
+        );
+        // 2 blocks in 16 seconds: 14 * 0.75 + 8 * 0.25
+        assert_eq!(
+            scheduler.on_success_at(104.into(), at(48)),
+            Duration::from_millis(12_500)
+        );
+    }
+
+    #[test]
+    fn delay_is_halved_if_no_block_was_produced() {
+        let mut scheduler = scheduler(5, 300);
+        let start = Instant::now();
+
+        scheduler.on_success_at(100.into(), start);
+        assert_eq!(
+            scheduler.on_success_at(100.into(), start),
+            Duration::from_secs(15)
+        );
+        scheduler.on_success_at(101.into(), start + Duration::from_secs(12));
+        assert_eq!(
+            scheduler.on_success_at(101.into(), start + Duration::from_secs(18)),
+            Duration::from_secs(6)
+        );
+        // the block time is measured from when the block was first observed
+        assert_eq!(
+            scheduler.on_success_at(102.into(), start + Duration::from_secs(24)),
+            Duration::from_secs(12)
+        );
+    }
+
+    #[test]
+    fn delay_is_bounded_by_the_intervals() {
+        let start = Instant::now();
+
+        let mut fast_blocks = scheduler(5, 300);
+        fast_blocks.on_success_at(100.into(), start);
+        assert_eq!(
+            fast_blocks.on_success_at(101.into(), start + Duration::from_secs(1)),
+            Duration::from_secs(2)
+        );
+
+        let mut slow_blocks = scheduler(5, 300);
+        slow_blocks.on_success_at(100.into(), start);
+        assert_eq!(
+            slow_blocks.on_success_at(101.into(), start + Duration::from_secs(60)),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn failures_back_off_exponentially_until_the_circuit_opens() {
+        let mut scheduler = scheduler(4, 60);
+
+        assert_jittered(scheduler.on_failure(), 4);
+        assert_jittered(scheduler.on_failure(), 8);
+        assert_jittered(scheduler.on_failure(), 16);
+        // circuit opened
+        assert_eq!(scheduler.on_failure(), Duration::from_secs(60));
+        assert_eq!(scheduler.on_failure(), Duration::from_secs(60));
+
+        // closed again after a success
+        scheduler.on_success(100.into());
+        assert_jittered(scheduler.on_failure(), 4);
+    }
+
+    #[test]
+    fn backoff_is_capped_at_the_cooldown() {
+        let mut scheduler = scheduler(10, 10);
+
+        assert_jittered(scheduler.on_failure(), 4);
+        assert_jittered(scheduler.on_failure(), 8);
+        assert_jittered(scheduler.on_failure(), 10);
+        assert_jittered(scheduler.on_failure(), 10);
+    }
+
+    #[test]
+    fn jitter_stays_within_half_of_the_duration() {
+        for _ in 0..100 {
+            assert_jittered(with_jitter(Duration::from_secs(10)), 10);
+        }
+    }
+}
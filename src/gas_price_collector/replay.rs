@@ -0,0 +1,349 @@
+use std::{fs, io, path::Path, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use log::info;
+use primitive_types::U256;
+use serde::Deserialize;
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    fee_state::FeeState,
+    static_fees::StaticFees,
+    storage::{any::AnyGasPriceStorage, ring_buffer::RingBufferStorage},
+    Collector, FeeSnapshot, HistoryPoint,
+};
+
+const LOG_TARGET: &str = "gas_price_collector::replay";
+
+const SOURCE: &str = "replay";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    IO(#[from] io::Error),
+    #[error("Invalid fee record at line {line}: {reason}")]
+    InvalidRecord { line: usize, reason: String },
+    #[error("Fee history file doesn't contain any record")]
+    Empty,
+    #[error("Replay speed must be a positive number, got {0}")]
+    InvalidSpeed(f64),
+}
+
+/// A recorded fee sample, the fields are the same as in the gas price history file
+/// (so a recorded history can be replayed as is).
+#[derive(Debug, Clone, Deserialize)]
+struct FeeRecord {
+    /// Unix timestamp in seconds when the fees were recorded
+    #[serde(alias = "fetched_at")]
+    timestamp: U256,
+    /// Timestamp of the block the fees were recorded at, if known
+    #[serde(default)]
+    block_timestamp: Option<U256>,
+    #[serde(flatten)]
+    fees: StaticFees,
+}
+
+impl FeeRecord {
+    /// Returns the snapshot served when the record is replayed. It is fetched at the time it is replayed,
+    /// but keeps the recorded time, so historical lookups by timestamp find it at that time.
+    fn snapshot(&self) -> FeeSnapshot {
+        let mut snapshot = self.fees.snapshot(U256::zero(), SOURCE);
+        snapshot.block_timestamp = Some(self.block_timestamp.unwrap_or(self.timestamp));
+        snapshot
+    }
+}
+
+/// Gas price collector that replays a recorded fee history (JSON lines or CSV file) at real or accelerated speed,
+/// e.g. to reproduce production incidents and run scenario tests offline.
+#[derive(Clone)]
+pub struct ReplayCollector {
+    records: Arc<Vec<FeeRecord>>,
+    /// Replay speed factor, 1.0 replays the history in real time
+    speed: f64,
+    state: Arc<FeeState>,
+}
+
+impl ReplayCollector {
+    /// Loads the fee history from the given file, `.csv` files are parsed as CSV with a header row,
+    /// anything else as JSON lines. Replayed samples are kept in memory only (for historical estimates).
+    ///
+    /// Records without a block number are numbered after the previous record (starting at 1),
+    /// so each of them is kept as a distinct sample.
+    pub fn load(path: &Path, speed: f64) -> Result<Self, Error> {
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(Error::InvalidSpeed(speed));
+        }
+        let content = fs::read_to_string(path)?;
+        let mut records = if path.extension().is_some_and(|extension| extension == "csv") {
+            parse_csv(&content)?
+        } else {
+            parse_json_lines(&content)?
+        };
+        if records.is_empty() {
+            return Err(Error::Empty);
+        }
+        records.sort_by_key(|record| record.timestamp);
+        let mut previous_block_number = U256::zero();
+        for record in &mut records {
+            let block_number = *record
+                .fees
+                .block_number
+                .get_or_insert(previous_block_number.saturating_add(U256::one()));
+            previous_block_number = block_number;
+        }
+        info!(target: LOG_TARGET, "Loaded {} fee records from {}", records.len(), path.display());
+        let storage = AnyGasPriceStorage::RingBuffer(RingBufferStorage::new(records.len()));
+
+        Ok(Self {
+            records: Arc::new(records),
+            speed,
+            state: Arc::new(FeeState::new(Arc::new(storage), None)),
+        })
+    }
+}
+
+#[async_trait]
+impl Collector for ReplayCollector {
+    type Error = Error;
+
+    /// Replays the records keeping their (scaled) time gaps, the last one is kept when the replay is finished.
+    async fn start(&self, cancel_token: CancellationToken) -> Result<(), Self::Error> {
+        info!(target: LOG_TARGET, "Replaying fee history at {}x speed", self.speed);
+        let mut previous_timestamp = None;
+        for record in self.records.iter() {
+            let timestamp = record.timestamp.low_u64();
+            if let Some(previous_timestamp) = previous_timestamp {
+                let gap = timestamp.saturating_sub(previous_timestamp) as f64;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs_f64(gap / self.speed)) => {}
+                    _ = cancel_token.cancelled() => return Ok(()),
+                }
+            }
+            previous_timestamp = Some(timestamp);
+            self.state.update(record.snapshot()).await;
+        }
+        info!(target: LOG_TARGET, "Fee history replay finished");
+        cancel_token.cancelled().await;
+
+        Ok(())
+    }
+
+    async fn fee_snapshot(&self) -> Option<FeeSnapshot> {
+        self.state.snapshot().await
+    }
+
+    async fn is_ready(&self) -> bool {
+        self.state.is_ready().await
+    }
+
+    async fn fee_snapshot_at(&self, point: HistoryPoint) -> Option<FeeSnapshot> {
+        self.state.snapshot_at(point).await
+    }
+}
+
+fn parse_json_lines(content: &str) -> Result<Vec<FeeRecord>, Error> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|error| Error::InvalidRecord {
+                line: index + 1,
+                reason: error.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Parses CSV records with a header row naming the columns the same as the JSON fields,
+/// values are decimal or `0x` prefixed hexadecimal numbers, empty values are considered missing.
+fn parse_csv(content: &str) -> Result<Vec<FeeRecord>, Error> {
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        return Ok(vec![]);
+    };
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+
+    lines
+        .map(|(index, line)| {
+            let invalid_record = |reason: String| Error::InvalidRecord {
+                line: index + 1,
+                reason,
+            };
+            let mut record = serde_json::Map::new();
+            for (column, value) in columns.iter().zip(line.split(',').map(str::trim)) {
+                if value.is_empty() {
+                    continue;
+                }
+                let number = parse_number(value)
+                    .map_err(|reason| invalid_record(format!("{column}: {reason}")))?;
+                record.insert(column.to_string(), format!("{number:#x}").into());
+            }
+            serde_json::from_value(record.into()).map_err(|error| invalid_record(error.to_string()))
+        })
+        .collect()
+}
+
+fn parse_number(value: &str) -> Result<U256, String> {
+    match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).map_err(|error| error.to_string()),
+        None => U256::from_dec_str(value).map_err(|error| error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_csv_with_hex_decimal_and_empty_values() {
+        let records = parse_csv(
+            "block_number, timestamp, base_fee_per_gas, max_priority_fee_per_gas, blob_base_fee\n\
+             21658683,1744830464,1000000000,0x5f5e100,1\n\
+             \n\
+             0x14a7c3c,0x6800000c,1125000000,,\n",
+        )
+        .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].fees.block_number, Some(U256::from(21_658_683)));
+        assert_eq!(records[0].timestamp, U256::from(1_744_830_464));
+        assert_eq!(
+            records[0].fees.max_priority_fee_per_gas,
+            U256::from(100_000_000)
+        );
+        assert_eq!(records[0].fees.blob_base_fee, Some(U256::one()));
+        assert_eq!(records[1].fees.block_number, Some(U256::from(21_658_684)));
+        assert_eq!(records[1].timestamp, U256::from(1_744_830_476));
+        assert_eq!(records[1].fees.base_fee_per_gas, U256::from(1_125_000_000));
+        assert_eq!(records[1].fees.max_priority_fee_per_gas, U256::zero());
+        assert_eq!(records[1].fees.blob_base_fee, None);
+    }
+
+    #[test]
+    fn csv_errors_report_the_line() {
+        let content = "timestamp,base_fee_per_gas\n1744830464,1000000000\n\n1744830476,0xinvalid\n";
+        let error = parse_csv(content).unwrap_err();
+        assert!(
+            matches!(&error, Error::InvalidRecord { line: 4, reason } if reason.starts_with("base_fee_per_gas")),
+            "{error:?}"
+        );
+
+        // missing required value
+        let error = parse_csv("timestamp,base_fee_per_gas\n1744830464,\n").unwrap_err();
+        assert!(matches!(error, Error::InvalidRecord { line: 2, .. }));
+        assert!(parse_csv("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parses_recorded_history_lines() {
+        let records = parse_json_lines(
+            r#"{"block_number":"0x14a7c3b","block_timestamp":"0x67e2a1bb","fetched_at":"0x67e2a1c0","gas_price":"0x3b9aca00","base_fee_per_gas":"0x3b9aca00","max_priority_fee_per_gas":"0x0","blob_base_fee":"0x1","source":"mainnet.infura.io"}
+
+{"timestamp":"0x67e2a1cc","base_fee_per_gas":"0x77359400"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].timestamp, U256::from(0x67e2a1c0));
+        assert_eq!(records[0].block_timestamp, Some(U256::from(0x67e2a1bb)));
+        assert_eq!(records[0].fees.gas_price, Some(U256::from(1_000_000_000)));
+        assert_eq!(records[1].timestamp, U256::from(0x67e2a1cc));
+        assert_eq!(records[1].fees.base_fee_per_gas, U256::from(2_000_000_000));
+    }
+
+    #[test]
+    fn json_lines_errors_report_the_line() {
+        let content =
+            "{\"timestamp\":\"0x1\",\"base_fee_per_gas\":\"0x1\"}\n\n{\"timestamp\":\"0x2\"}\n";
+        let error = parse_json_lines(content).unwrap_err();
+        assert!(
+            matches!(error, Error::InvalidRecord { line: 3, .. }),
+            "{error:?}"
+        );
+    }
+
+    /// Replays the given CSV fee history at high speed until its record recorded at `last_timestamp` is replayed.
+    async fn replay(content: &str, last_timestamp: u64) -> ReplayCollector {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("history.csv");
+        fs::write(&path, content).unwrap();
+        let collector = ReplayCollector::load(&path, 1_000.0).unwrap();
+
+        let cancel_token = CancellationToken::new();
+        let running = tokio::spawn({
+            let collector = collector.clone();
+            let cancel_token = cancel_token.clone();
+            async move { collector.start(cancel_token).await }
+        });
+        let replayed =
+            async {
+                while collector.fee_snapshot().await.is_none_or(|snapshot| {
+                    snapshot.block_timestamp != Some(U256::from(last_timestamp))
+                }) {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            };
+        tokio::time::timeout(Duration::from_secs(5), replayed)
+            .await
+            .unwrap();
+        cancel_token.cancel();
+        running.await.unwrap().unwrap();
+
+        collector
+    }
+
+    #[tokio::test]
+    async fn replayed_snapshots_keep_the_recorded_time() {
+        let collector = replay(
+            "block_number,timestamp,base_fee_per_gas\n100,1744830464,1000000000\n101,1744830476,2000000000\n",
+            1_744_830_476,
+        )
+        .await;
+
+        let latest = collector.fee_snapshot().await.unwrap();
+        assert_eq!(latest.block_number, U256::from(101));
+        // served as fresh fee data
+        assert_eq!(latest.age_seconds(), 0);
+        let at_first = collector
+            .fee_snapshot_at(HistoryPoint::Timestamp(1_744_830_470.into()))
+            .await
+            .unwrap();
+        assert_eq!(at_first.block_number, U256::from(100));
+        assert_eq!(at_first.base_fee_per_gas, U256::from(1_000_000_000));
+    }
+
+    #[tokio::test]
+    async fn records_without_block_number_are_kept_apart() {
+        let collector = replay(
+            "timestamp,base_fee_per_gas\n1744830464,1000000000\n1744830476,2000000000\n1744830488,3000000000\n",
+            1_744_830_488,
+        )
+        .await;
+
+        assert_eq!(
+            collector.fee_snapshot().await.unwrap().block_number,
+            U256::from(3)
+        );
+        for (timestamp, block_number, base_fee_per_gas) in [
+            (1_744_830_470u64, 1u64, 1_000_000_000u64),
+            (1_744_830_480, 2, 2_000_000_000),
+        ] {
+            let snapshot = collector
+                .fee_snapshot_at(HistoryPoint::Timestamp(timestamp.into()))
+                .await
+                .unwrap();
+            assert_eq!(snapshot.block_number, U256::from(block_number));
+            assert_eq!(snapshot.base_fee_per_gas, U256::from(base_fee_per_gas));
+        }
+        let at_block = collector
+            .fee_snapshot_at(HistoryPoint::BlockNumber(2.into()))
+            .await
+            .unwrap();
+        assert_eq!(at_block.base_fee_per_gas, U256::from(2_000_000_000));
+    }
+}
@@ -0,0 +1,99 @@
+use std::{convert::Infallible, fs, io, path::Path};
+
+use async_trait::async_trait;
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+use super::{unix_timestamp, Collector, FeeSnapshot, HistoryPoint};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    IO(#[from] io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Fee values set manually instead of collected from an ethereum client (all in WEI).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaticFees {
+    /// Block number reported with the fees
+    #[serde(default)]
+    pub block_number: Option<U256>,
+    pub base_fee_per_gas: U256,
+    #[serde(default)]
+    pub max_priority_fee_per_gas: U256,
+    /// Legacy gas price, base fee + priority fee if not set
+    #[serde(default)]
+    pub gas_price: Option<U256>,
+    #[serde(default)]
+    pub blob_base_fee: Option<U256>,
+}
+
+impl StaticFees {
+    /// Returns a snapshot of the fees as if they were fetched now,
+    /// `block_number` is used if the fees don't set one.
+    pub fn snapshot(&self, block_number: U256, source: &str) -> FeeSnapshot {
+        FeeSnapshot {
+            block_number: self.block_number.unwrap_or(block_number),
+            block_timestamp: None,
+            fetched_at: unix_timestamp().into(),
+            gas_price: self.gas_price.unwrap_or_else(|| {
+                self.base_fee_per_gas
+                    .saturating_add(self.max_priority_fee_per_gas)
+            }),
+            base_fee_per_gas: self.base_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+            blob_base_fee: self.blob_base_fee,
+            source: source.to_string(),
+        }
+    }
+}
+
+/// Gas price collector that serves fixed fee values (from CLI arguments or a JSON file) without any external calls,
+/// e.g. for offline development and deterministic tests.
+#[derive(Clone)]
+pub struct StaticCollector {
+    fees: StaticFees,
+}
+
+impl StaticCollector {
+    pub fn new(fees: StaticFees) -> Self {
+        Self { fees }
+    }
+
+    /// Loads the fees from the given JSON file.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let fees = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Ok(Self::new(fees))
+    }
+
+    fn snapshot(&self) -> FeeSnapshot {
+        self.fees.snapshot(U256::zero(), "static")
+    }
+}
+
+#[async_trait]
+impl Collector for StaticCollector {
+    type Error = Infallible;
+
+    async fn start(&self, cancel_token: CancellationToken) -> Result<(), Self::Error> {
+        cancel_token.cancelled().await;
+        Ok(())
+    }
+
+    async fn fee_snapshot(&self) -> Option<FeeSnapshot> {
+        Some(self.snapshot())
+    }
+
+    async fn is_ready(&self) -> bool {
+        true
+    }
+
+    /// The fees are the same at any point in time.
+    async fn fee_snapshot_at(&self, _point: HistoryPoint) -> Option<FeeSnapshot> {
+        Some(self.snapshot())
+    }
+}
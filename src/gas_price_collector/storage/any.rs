@@ -0,0 +1,55 @@
+use std::convert::Infallible;
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+use super::{
+    file::{self, FileStorage},
+    ring_buffer::RingBufferStorage,
+    FeeSnapshot, GasPriceStorage, HistoryPoint,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("File storage error: {0}")]
+    File(#[from] file::Error),
+}
+
+impl From<Infallible> for Error {
+    fn from(error: Infallible) -> Self {
+        match error {}
+    }
+}
+
+/// Gas price storage that is selected at startup (through CLI arguments).
+#[derive(Clone)]
+pub enum AnyGasPriceStorage {
+    RingBuffer(RingBufferStorage),
+    File(FileStorage),
+}
+
+#[async_trait]
+impl GasPriceStorage for AnyGasPriceStorage {
+    type Error = Error;
+
+    async fn record(&self, sample: FeeSnapshot) -> Result<(), Self::Error> {
+        match self {
+            AnyGasPriceStorage::RingBuffer(storage) => Ok(storage.record(sample).await?),
+            AnyGasPriceStorage::File(storage) => Ok(storage.record(sample).await?),
+        }
+    }
+
+    async fn latest(&self) -> Result<Option<FeeSnapshot>, Self::Error> {
+        match self {
+            AnyGasPriceStorage::RingBuffer(storage) => Ok(storage.latest().await?),
+            AnyGasPriceStorage::File(storage) => Ok(storage.latest().await?),
+        }
+    }
+
+    async fn at(&self, point: HistoryPoint) -> Result<Option<FeeSnapshot>, Self::Error> {
+        match self {
+            AnyGasPriceStorage::RingBuffer(storage) => Ok(storage.at(point).await?),
+            AnyGasPriceStorage::File(storage) => Ok(storage.at(point).await?),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    convert::Infallible,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use log::{info, warn};
+use thiserror::Error;
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    sync::Mutex,
+};
+
+use super::{ring_buffer::RingBufferStorage, FeeSnapshot, GasPriceStorage, HistoryPoint};
+
+const LOG_TARGET: &str = "gas_price_collector::storage::file";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    IO(#[from] io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl From<Infallible> for Error {
+    fn from(error: Infallible) -> Self {
+        match error {}
+    }
+}
+
+/// The file samples are appended to.
+struct HistoryFile {
+    file: File,
+    /// Number of lines in the file
+    lines: usize,
+}
+
+/// On-disk storage that appends every sample to a JSON lines file,
+/// the last `capacity` samples are reloaded on startup and kept in memory for lookups.
+///
+/// Once the file holds twice as many lines as `capacity`, it is rewritten with only the kept samples,
+/// so it doesn't grow without limits.
+#[derive(Clone)]
+pub struct FileStorage {
+    path: Arc<PathBuf>,
+    capacity: usize,
+    file: Arc<Mutex<HistoryFile>>,
+    samples: RingBufferStorage,
+}
+
+impl FileStorage {
+    /// Opens (or creates) the given file and loads the samples recorded earlier.
+    pub async fn open(path: &Path, capacity: usize) -> Result<Self, Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .await?;
+
+        let samples = RingBufferStorage::new(capacity);
+        let mut reader = BufReader::new(&mut file);
+        let mut line = String::new();
+        let mut loaded = 0;
+        let mut lines = 0;
+        let mut terminated = true;
+        while reader.read_line(&mut line).await? > 0 {
+            lines += 1;
+            terminated = line.ends_with('\n');
+            // a partially written line (e.g. after a crash) is skipped
+            if !line.trim().is_empty() {
+                match serde_json::from_str(&line) {
+                    Ok(sample) => {
+                        samples.push(sample).await;
+                        loaded += 1;
+                    }
+                    Err(error) => warn!(target: LOG_TARGET, "Skipping invalid sample: {error}"),
+                }
+            }
+            line.clear();
+        }
+        // new samples must start on a new line
+        if !terminated {
+            file.write_all(b"\n").await?;
+        }
+        info!(target: LOG_TARGET, "Loaded {loaded} gas price samples from {}", path.display());
+
+        let storage = Self {
+            path: Arc::new(path.to_path_buf()),
+            capacity,
+            file: Arc::new(Mutex::new(HistoryFile { file, lines })),
+            samples,
+        };
+        if lines > capacity {
+            storage.compact(&mut *storage.file.lock().await).await?;
+        }
+
+        Ok(storage)
+    }
+
+    /// Rewrites the file with the samples kept in memory,
+    /// the new file replaces the old one only when it is completely written.
+    async fn compact(&self, history_file: &mut HistoryFile) -> Result<(), Error> {
+        let samples = self.samples.samples().await;
+        let mut content = Vec::new();
+        for sample in &samples {
+            serde_json::to_writer(&mut content, sample)?;
+            content.push(b'\n');
+        }
+
+        let compacted_path = self.path.with_extension("compacting");
+        fs::write(&compacted_path, content).await?;
+        fs::rename(&compacted_path, self.path.as_ref()).await?;
+        history_file.file = OpenOptions::new()
+            .append(true)
+            .open(self.path.as_ref())
+            .await?;
+        info!(
+            target: LOG_TARGET,
+            "Compacted {} from {} to {} samples",
+            self.path.display(),
+            history_file.lines,
+            samples.len()
+        );
+        history_file.lines = samples.len();
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl GasPriceStorage for FileStorage {
+    type Error = Error;
+
+    async fn record(&self, sample: FeeSnapshot) -> Result<(), Self::Error> {
+        let mut line = serde_json::to_vec(&sample)?;
+        line.push(b'\n');
+        // the file stays locked until the sample is kept in memory, so compaction sees the same samples as the file
+        let mut history_file = self.file.lock().await;
+        self.samples.push(sample).await;
+        history_file.file.write_all(&line).await?;
+        history_file.file.flush().await?;
+        history_file.lines += 1;
+        if history_file.lines >= self.capacity.saturating_mul(2) {
+            self.compact(&mut history_file).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn latest(&self) -> Result<Option<FeeSnapshot>, Self::Error> {
+        Ok(self.samples.latest().await?)
+    }
+
+    async fn at(&self, point: HistoryPoint) -> Result<Option<FeeSnapshot>, Self::Error> {
+        Ok(self.samples.at(point).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitive_types::U256;
+
+    use super::*;
+
+    fn sample(block_number: u64) -> FeeSnapshot {
+        FeeSnapshot {
+            block_number: block_number.into(),
+            block_timestamp: Some(block_number.into()),
+            fetched_at: block_number.into(),
+            gas_price: U256::from(block_number),
+            base_fee_per_gas: U256::zero(),
+            max_priority_fee_per_gas: U256::zero(),
+            blob_base_fee: None,
+            source: String::new(),
+        }
+    }
+
+    async fn block_numbers(path: &Path) -> Vec<u64> {
+        fs::read_to_string(path)
+            .await
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<FeeSnapshot>(line)
+                    .unwrap()
+                    .block_number
+                    .as_u64()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn samples_are_reloaded() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("history.jsonl");
+        let storage = FileStorage::open(&path, 10).await.unwrap();
+        for block_number in 1..=3 {
+            storage.record(sample(block_number)).await.unwrap();
+        }
+        drop(storage);
+
+        let storage = FileStorage::open(&path, 10).await.unwrap();
+
+        let latest = storage.latest().await.unwrap().unwrap();
+        assert_eq!(latest.block_number, U256::from(3));
+        let at = storage
+            .at(HistoryPoint::BlockNumber(2.into()))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(at.block_number, U256::from(2));
+    }
+
+    #[tokio::test]
+    async fn partial_line_is_skipped() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("history.jsonl");
+        let mut content = serde_json::to_string(&sample(1)).unwrap();
+        content.push_str("\n{\"block_number\":");
+        fs::write(&path, content).await.unwrap();
+
+        let storage = FileStorage::open(&path, 10).await.unwrap();
+        storage.record(sample(2)).await.unwrap();
+
+        // the new sample starts on a new line
+        let content = fs::read_to_string(&path).await.unwrap();
+        assert_eq!(content.lines().count(), 3);
+        let storage = FileStorage::open(&path, 10).await.unwrap();
+        assert_eq!(
+            storage.latest().await.unwrap().unwrap().block_number,
+            U256::from(2)
+        );
+    }
+
+    #[tokio::test]
+    async fn file_is_compacted_to_capacity() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("history.jsonl");
+        let storage = FileStorage::open(&path, 3).await.unwrap();
+        for block_number in 1..=5 {
+            storage.record(sample(block_number)).await.unwrap();
+        }
+        assert_eq!(block_numbers(&path).await, [1, 2, 3, 4, 5]);
+
+        // the 6th line reaches twice the capacity
+        storage.record(sample(6)).await.unwrap();
+        assert_eq!(block_numbers(&path).await, [4, 5, 6]);
+
+        // appending continues in the compacted file
+        storage.record(sample(7)).await.unwrap();
+        assert_eq!(block_numbers(&path).await, [4, 5, 6, 7]);
+    }
+
+    #[tokio::test]
+    async fn oversized_file_is_compacted_on_open() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("history.jsonl");
+        let storage = FileStorage::open(&path, 10).await.unwrap();
+        for block_number in 1..=5 {
+            storage.record(sample(block_number)).await.unwrap();
+        }
+        drop(storage);
+
+        FileStorage::open(&path, 2).await.unwrap();
+
+        assert_eq!(block_numbers(&path).await, [4, 5]);
+    }
+}
@@ -0,0 +1,26 @@
+use async_trait::async_trait;
+
+use super::{FeeSnapshot, HistoryPoint};
+
+pub mod any;
+pub mod file;
+pub mod ring_buffer;
+
+/// Maximum number of gas price samples kept for lookups (~30 days with the default polling interval).
+pub const DEFAULT_CAPACITY: usize = 86_400;
+
+/// The trait that all gas price history storages must implement.
+/// It gives the chance to let the implementation handle where collected gas prices are kept (memory, disk etc...).
+#[async_trait]
+pub trait GasPriceStorage: Send + Sync {
+    type Error: std::fmt::Display;
+
+    /// Records a newly collected gas price sample.
+    async fn record(&self, sample: FeeSnapshot) -> Result<(), Self::Error>;
+
+    /// Returns the last recorded sample.
+    async fn latest(&self) -> Result<Option<FeeSnapshot>, Self::Error>;
+
+    /// Returns the last sample recorded at or before the given point.
+    async fn at(&self, point: HistoryPoint) -> Result<Option<FeeSnapshot>, Self::Error>;
+}
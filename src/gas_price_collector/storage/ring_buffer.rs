@@ -0,0 +1,68 @@
+use std::{collections::VecDeque, convert::Infallible, sync::Arc};
+
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use super::{FeeSnapshot, GasPriceStorage, HistoryPoint};
+
+/// In memory storage that keeps the last `capacity` samples, older samples are dropped.
+#[derive(Clone)]
+pub struct RingBufferStorage {
+    capacity: usize,
+    samples: Arc<RwLock<VecDeque<FeeSnapshot>>>,
+}
+
+impl RingBufferStorage {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: Arc::new(RwLock::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// Adds the sample, a sample of the same block as the last one replaces it.
+    pub(super) async fn push(&self, sample: FeeSnapshot) {
+        let mut samples = self.samples.write().await;
+        if samples
+            .back()
+            .is_some_and(|last| last.block_number == sample.block_number)
+        {
+            samples.pop_back();
+        }
+        if samples.len() >= self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    /// Returns the kept samples in the order they were recorded.
+    pub(super) async fn samples(&self) -> Vec<FeeSnapshot> {
+        self.samples.read().await.iter().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl GasPriceStorage for RingBufferStorage {
+    type Error = Infallible;
+
+    async fn record(&self, sample: FeeSnapshot) -> Result<(), Self::Error> {
+        self.push(sample).await;
+        Ok(())
+    }
+
+    async fn latest(&self) -> Result<Option<FeeSnapshot>, Self::Error> {
+        Ok(self.samples.read().await.back().cloned())
+    }
+
+    async fn at(&self, point: HistoryPoint) -> Result<Option<FeeSnapshot>, Self::Error> {
+        // samples are recorded in order, so the first match from the back is the closest one
+        Ok(self
+            .samples
+            .read()
+            .await
+            .iter()
+            .rev()
+            .find(|sample| sample.is_at_or_before(point))
+            .cloned())
+    }
+}
@@ -0,0 +1,461 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    primitives::B256,
+    pubsub::Subscription,
+    rpc::client::{ClientBuilder, RpcClient},
+    transports::{ws::WsConnect, RpcError, TransportErrorKind},
+};
+use async_trait::async_trait;
+use log::{debug, error, info, warn};
+use primitive_types::U256;
+use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+use url::Position;
+
+use super::{
+    endpoints::{self, BlockHeader, RpcEndpoint, RpcEndpoints},
+    fee_state::FeeState,
+    polling::{self, PollingConfig},
+    storage::any::AnyGasPriceStorage,
+    unix_timestamp, Collector, FeeSnapshot, HistoryPoint,
+};
+use crate::rpc_budget::layer::RpcBudgetLayer;
+
+const LOG_TARGET: &str = "gas_price_collector::websocket";
+
+/// Delay of the first reconnection attempt, doubled after every failed attempt.
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("WebSocket JSON-RPC transport error: {0}")]
+    RpcTransport(#[from] RpcError<TransportErrorKind>),
+    #[error("WebSocket client doesn't support subscriptions")]
+    SubscriptionsNotSupported,
+    #[error("Ethereum client JSON-RPC error: {0}")]
+    Endpoints(#[from] endpoints::Error),
+}
+
+/// Gas price collector that updates the fee data on every new block through a WebSocket `newHeads` subscription.
+/// The base fee of the next block is derived from the received header, the rest of the fee data is refreshed
+/// from the node once per poll interval (the blob base fee also when the excess blob gas changes).
+/// While the WebSocket connection is down, fee data is polled from the ethereum JSON RPC endpoints.
+#[derive(Clone)]
+pub struct WebSocketCollector {
+    ws_url: url::Url,
+    /// Endpoints that are polled while the WebSocket connection is down
+    endpoints: Arc<RpcEndpoints>,
+    state: Arc<FeeState>,
+    /// The poll interval is used as the fee data refresh interval and the maximum reconnection delay too
+    polling: PollingConfig,
+}
+
+/// Fee data fetched from the node that is carried over to the following blocks until it is refreshed.
+struct FetchedFees {
+    /// Fee snapshot of the last block
+    snapshot: FeeSnapshot,
+    /// When the gas price and the priority fee were fetched
+    refreshed_at: Instant,
+    /// Excess blob gas of the block the blob base fee was fetched at
+    excess_blob_gas: Option<U256>,
+}
+
+impl WebSocketCollector {
+    /// Creates a new collector, the last stored gas price is used until a fresh one is collected
+    /// only if it is not older than `max_stale_age`.
+    pub fn new(
+        ws_url: url::Url,
+        endpoints: RpcEndpoints,
+        storage: Arc<AnyGasPriceStorage>,
+        max_stale_age: Option<Duration>,
+        polling: PollingConfig,
+    ) -> Self {
+        Self {
+            ws_url,
+            endpoints: Arc::new(endpoints),
+            state: Arc::new(FeeState::new(storage, max_stale_age)),
+            polling,
+        }
+    }
+
+    /// Returns the ethereum JSON RPC client of the primary endpoint, so other services can reuse the same connection.
+    pub fn eth_client(&self) -> RpcClient {
+        self.endpoints.primary_client()
+    }
+
+    /// Connects to the WebSocket endpoint and subscribes to new block headers.
+    async fn subscribe(&self) -> Result<(RpcEndpoint, Subscription<BlockHeader>), Error> {
+        let client = ClientBuilder::default()
+            .layer(RpcBudgetLayer::new(self.endpoints.budget()))
+            .ws(WsConnect::new(self.ws_url.as_str()))
+            .await?;
+        let id: B256 = client.request("eth_subscribe", ("newHeads",)).await?;
+        let headers = client
+            .pubsub_frontend()
+            .ok_or(Error::SubscriptionsNotSupported)?
+            .get_subscription(id)
+            .await?
+            .into_typed::<BlockHeader>();
+        let source = self.ws_url[Position::BeforeHost..Position::AfterPort].to_string();
+        info!(target: LOG_TARGET, "Subscribed to new block headers");
+
+        Ok((RpcEndpoint::from_client(client, source), headers))
+    }
+
+    /// Subscribes to new block headers, retrying after the given delay that is doubled after every failed attempt
+    /// (up to the poll interval).
+    async fn connect(
+        &self,
+        reconnect_delay: &mut Duration,
+    ) -> (RpcEndpoint, Subscription<BlockHeader>) {
+        loop {
+            if !reconnect_delay.is_zero() {
+                info!(target: LOG_TARGET, "Reconnecting in {}s", reconnect_delay.as_secs());
+                tokio::time::sleep(*reconnect_delay).await;
+            }
+            match self.subscribe().await {
+                Ok(subscription) => return subscription,
+                Err(error) => {
+                    error!(target: LOG_TARGET, "WebSocket subscription failed: {error:?}")
+                }
+            }
+            *reconnect_delay = (*reconnect_delay * 2)
+                .max(INITIAL_RECONNECT_DELAY)
+                .min(self.polling.interval);
+        }
+    }
+
+    /// Updates the fee data on every new block, returns when the subscription is closed or the collector is cancelled.
+    /// If no block header is received for a poll interval (e.g. the connection is being restored),
+    /// fee data is polled from the JSON RPC endpoints.
+    /// Block headers are skipped while updating on every block would exceed the JSON RPC budget.
+    async fn follow_new_heads(
+        &self,
+        endpoint: &RpcEndpoint,
+        mut headers: Subscription<BlockHeader>,
+        cancel_token: &CancellationToken,
+    ) {
+        let budget = self.endpoints.budget();
+        let mut fetched = None;
+        let mut next_update = Instant::now();
+        loop {
+            tokio::select! {
+                header = headers.recv() => match header {
+                    Ok(header) if Instant::now() < next_update => {
+                        debug!(target: LOG_TARGET, "New block: {}, skipped to stay within the JSON RPC budget", header.number);
+                    }
+                    Ok(header) => {
+                        debug!(target: LOG_TARGET, "New block: {}", header.number);
+                        let requests_before = budget.requests();
+                        match self.update_gas_price(endpoint, header, fetched.take()).await {
+                            Ok(updated) => fetched = Some(updated),
+                            Err(error) => error!(target: LOG_TARGET, "Failed to update gas price: {error:?}"),
+                        }
+                        next_update = Instant::now()
+                            + budget.pacing_delay(budget.requests().saturating_sub(requests_before));
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!(target: LOG_TARGET, "Skipped {skipped} block headers");
+                    }
+                    Err(RecvError::Closed) => return,
+                },
+                _ = tokio::time::sleep(self.polling.interval) => {
+                    warn!(target: LOG_TARGET, "No new block header received for {}s", self.polling.interval.as_secs());
+                    if let Err(error) = self.poll_gas_price().await {
+                        error!(target: LOG_TARGET, "Failed to poll gas price: {error:?}");
+                    }
+                }
+                _ = cancel_token.cancelled() => return,
+            }
+        }
+    }
+
+    /// Updates the fee data with the given new block header. The base fee is derived from the header,
+    /// the rest of the fee data is carried over from the previous block until the refresh interval elapses.
+    async fn update_gas_price(
+        &self,
+        endpoint: &RpcEndpoint,
+        header: BlockHeader,
+        fetched: Option<FetchedFees>,
+    ) -> Result<FetchedFees, Error> {
+        let carried = fetched
+            .filter(|fetched| fetched.refreshed_at.elapsed() < self.polling.interval)
+            .and_then(|fetched| Some((next_block_snapshot(&fetched.snapshot, &header)?, fetched)));
+        let fetched = match carried {
+            Some((mut snapshot, fetched)) => {
+                let excess_blob_gas = if header.excess_blob_gas == fetched.excess_blob_gas {
+                    fetched.excess_blob_gas
+                } else {
+                    match endpoint.blob_base_fee().await {
+                        Ok(blob_base_fee) => {
+                            snapshot.blob_base_fee = Some(blob_base_fee);
+                            header.excess_blob_gas
+                        }
+                        Err(error) => {
+                            debug!(target: LOG_TARGET, "Failed to get blob base fee: {error:?}");
+                            fetched.excess_blob_gas
+                        }
+                    }
+                };
+                FetchedFees {
+                    snapshot,
+                    refreshed_at: fetched.refreshed_at,
+                    excess_blob_gas,
+                }
+            }
+            None => {
+                let excess_blob_gas = header.excess_blob_gas;
+                let next_base_fee = header.next_base_fee();
+                let mut snapshot = endpoint.fetch_with_header(Some(header)).await?;
+                if let Some(next_base_fee) = next_base_fee {
+                    snapshot.base_fee_per_gas = next_base_fee;
+                }
+                FetchedFees {
+                    snapshot,
+                    refreshed_at: Instant::now(),
+                    excess_blob_gas,
+                }
+            }
+        };
+        self.state.update(fetched.snapshot.clone()).await;
+
+        Ok(fetched)
+    }
+
+    /// Fetches the fee data from the JSON RPC endpoints, returns the block number it was collected at.
+    async fn poll_gas_price(&self) -> Result<U256, Error> {
+        let snapshot = self.endpoints.fetch().await?;
+        let block_number = snapshot.block_number;
+        self.state.update(snapshot).await;
+
+        Ok(block_number)
+    }
+}
+
+/// Returns the fee snapshot of the block of the given header based on the snapshot of the previous block:
+/// the base fee is the one of the next block derived from the header, the gas price moves with it.
+/// Returns `None` if the next base fee can't be derived from the header.
+fn next_block_snapshot(previous: &FeeSnapshot, header: &BlockHeader) -> Option<FeeSnapshot> {
+    let base_fee_per_gas = header.next_base_fee()?;
+    Some(FeeSnapshot {
+        block_number: header.number,
+        block_timestamp: header.timestamp,
+        fetched_at: unix_timestamp().into(),
+        gas_price: previous
+            .gas_price
+            .saturating_sub(previous.base_fee_per_gas)
+            .saturating_add(base_fee_per_gas),
+        base_fee_per_gas,
+        max_priority_fee_per_gas: previous.max_priority_fee_per_gas,
+        blob_base_fee: previous.blob_base_fee,
+        source: previous.source.clone(),
+    })
+}
+
+#[async_trait]
+impl Collector for WebSocketCollector {
+    type Error = Error;
+
+    async fn start(&self, cancel_token: CancellationToken) -> Result<(), Self::Error> {
+        if let Err(error) = self.state.load_last().await {
+            error!(target: LOG_TARGET, "Failed to load last gas price: {error:?}");
+        }
+
+        let budget = self.endpoints.budget();
+        let mut reconnect_delay = Duration::ZERO;
+        while !cancel_token.is_cancelled() {
+            // fall back to scheduled polling until the subscription is (re)established
+            let (endpoint, headers) = tokio::select! {
+                subscription = self.connect(&mut reconnect_delay) => subscription,
+                _ = polling::run(self.polling, &budget, &cancel_token, || self.poll_gas_price()) => break,
+            };
+            let connected_at = Instant::now();
+            self.follow_new_heads(&endpoint, headers, &cancel_token)
+                .await;
+            if cancel_token.is_cancelled() {
+                break;
+            }
+            warn!(target: LOG_TARGET, "WebSocket subscription closed");
+            // a connection that was alive for a while is reconnected without backoff
+            reconnect_delay = if connected_at.elapsed() > self.polling.interval {
+                INITIAL_RECONNECT_DELAY
+            } else {
+                (reconnect_delay * 2)
+                    .max(INITIAL_RECONNECT_DELAY)
+                    .min(self.polling.interval)
+            };
+        }
+
+        Ok(())
+    }
+
+    async fn fee_snapshot(&self) -> Option<FeeSnapshot> {
+        self.state.snapshot().await
+    }
+
+    async fn is_ready(&self) -> bool {
+        self.state.is_ready().await
+    }
+
+    async fn fee_snapshot_at(&self, point: HistoryPoint) -> Option<FeeSnapshot> {
+        self.state.snapshot_at(point).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        gas_price_collector::storage::ring_buffer::RingBufferStorage, rpc_budget::RpcBudget,
+        test_utils::StubRpcServer,
+    };
+
+    async fn node() -> StubRpcServer {
+        StubRpcServer::start(|method: &str, _: &Value| match method {
+            "eth_gasPrice" => Some(json!("0x4a817c800")),
+            "eth_getBlockByNumber" => Some(json!({
+                "number": "0x64",
+                "baseFeePerGas": "0x3b9aca00",
+            })),
+            "eth_maxPriorityFeePerGas" => Some(json!("0x3b9aca00")),
+            "eth_blobBaseFee" => Some(json!("0x2")),
+            _ => None,
+        })
+        .await
+    }
+
+    fn collector(ws_url: &str, node: &StubRpcServer) -> WebSocketCollector {
+        let endpoints = RpcEndpoints::new(
+            std::slice::from_ref(&node.url),
+            endpoints::EndpointStrategy::Failover,
+            2,
+            None,
+            Arc::new(RpcBudget::new(None)),
+        )
+        .unwrap();
+        WebSocketCollector::new(
+            ws_url.parse().unwrap(),
+            endpoints,
+            Arc::new(AnyGasPriceStorage::RingBuffer(RingBufferStorage::new(10))),
+            None,
+            PollingConfig {
+                interval: Duration::from_secs(30),
+                min_interval: Duration::from_secs(2),
+                failure_threshold: 5,
+                cooldown: Duration::from_secs(60),
+            },
+        )
+    }
+
+    /// Full block at 1 GWEI base fee, so the next base fee is 1.125 GWEI.
+    fn header(number: u64, excess_blob_gas: u64) -> BlockHeader {
+        BlockHeader {
+            number: number.into(),
+            timestamp: None,
+            base_fee_per_gas: U256::from(1_000_000_000),
+            gas_used: Some(U256::from(30_000_000)),
+            gas_limit: Some(U256::from(30_000_000)),
+            excess_blob_gas: Some(excess_blob_gas.into()),
+        }
+    }
+
+    #[test]
+    fn next_block_snapshot_moves_the_gas_price_with_the_base_fee() {
+        let previous = FeeSnapshot {
+            block_number: U256::from(99),
+            block_timestamp: None,
+            fetched_at: U256::zero(),
+            gas_price: U256::from(3_000_000_000u64),
+            base_fee_per_gas: U256::from(2_000_000_000),
+            max_priority_fee_per_gas: U256::from(1_000_000_000),
+            blob_base_fee: Some(U256::from(5)),
+            source: "node".to_string(),
+        };
+
+        let snapshot = next_block_snapshot(&previous, &header(100, 0)).unwrap();
+
+        assert_eq!(snapshot.block_number, U256::from(100));
+        assert_eq!(snapshot.base_fee_per_gas, U256::from(1_125_000_000));
+        assert_eq!(snapshot.gas_price, U256::from(2_125_000_000u64));
+        assert_eq!(
+            snapshot.max_priority_fee_per_gas,
+            previous.max_priority_fee_per_gas
+        );
+        assert_eq!(snapshot.blob_base_fee, previous.blob_base_fee);
+    }
+
+    #[tokio::test]
+    async fn fee_data_is_refreshed_only_when_needed() {
+        let node = node().await;
+        let collector = collector("ws://127.0.0.1:1", &node);
+        let endpoint = RpcEndpoint::new(node.url.clone(), Arc::new(RpcBudget::new(None)));
+
+        // the first block fetches everything but the header
+        let fetched = collector
+            .update_gas_price(&endpoint, header(100, 0), None)
+            .await
+            .unwrap();
+        assert_eq!(node.count("eth_gasPrice"), 1);
+        assert_eq!(node.count("eth_getBlockByNumber"), 0);
+        assert_eq!(fetched.snapshot.base_fee_per_gas, U256::from(1_125_000_000));
+
+        // the following blocks are derived from the header
+        let fetched = collector
+            .update_gas_price(&endpoint, header(101, 0), Some(fetched))
+            .await
+            .unwrap();
+        assert_eq!(node.requests().len(), 3);
+        assert_eq!(fetched.snapshot.block_number, U256::from(101));
+
+        // only the blob base fee is fetched when the excess blob gas changes
+        let fetched = collector
+            .update_gas_price(&endpoint, header(102, 131_072), Some(fetched))
+            .await
+            .unwrap();
+        assert_eq!(node.requests().len(), 4);
+        assert_eq!(node.count("eth_blobBaseFee"), 2);
+        assert_eq!(
+            collector.fee_snapshot().await.unwrap().block_number,
+            U256::from(102)
+        );
+
+        // everything is fetched again once the refresh interval elapses
+        let expired = FetchedFees {
+            refreshed_at: Instant::now() - Duration::from_secs(31),
+            ..fetched
+        };
+        collector
+            .update_gas_price(&endpoint, header(103, 131_072), Some(expired))
+            .await
+            .unwrap();
+        assert_eq!(node.count("eth_gasPrice"), 2);
+    }
+
+    #[tokio::test]
+    async fn fallback_polling_is_scheduled() {
+        let node = node().await;
+        // nothing listens on this port, so the subscription keeps failing
+        let collector = collector("ws://127.0.0.1:1", &node);
+        let cancel_token = CancellationToken::new();
+        let running = tokio::spawn({
+            let collector = collector.clone();
+            let cancel_token = cancel_token.clone();
+            async move { collector.start(cancel_token).await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(2_500)).await;
+        cancel_token.cancel();
+        running.await.unwrap().unwrap();
+
+        // polled once, the next poll is scheduled for the poll interval instead of every reconnection attempt
+        assert_eq!(node.count("eth_gasPrice"), 1);
+        assert!(collector.fee_snapshot().await.is_some());
+    }
+}
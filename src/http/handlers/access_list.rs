@@ -0,0 +1,77 @@
+use axum::{extract::State, http::StatusCode, Json};
+use ethereum::AccessList;
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fee_estimator::{
+        access_list::AccessListEstimate, staleness::FeeDataInfo, validation::ValidationError,
+        Error, Estimator, Transaction,
+    },
+    gas_price_collector::{any::AnyCollector, manual_override::OverridableCollector},
+    state::{any::AnyStateProvider, snapshot::StateSnapshot},
+};
+
+use super::error_status;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessListRequest {
+    #[serde(flatten)]
+    transaction: Transaction,
+    /// State to execute the transaction on instead of the default state
+    #[serde(default)]
+    state_snapshot: Option<StateSnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessListResponse {
+    access_list: AccessList,
+    estimated_fee_wei: U256,
+    estimated_fee_with_access_list_wei: U256,
+    error: Option<String>,
+    #[serde(flatten)]
+    fee_data: Option<FeeDataInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validation_error: Option<ValidationError>,
+}
+
+impl AccessListResponse {
+    pub fn success(estimate: AccessListEstimate, fee_data: Option<FeeDataInfo>) -> Self {
+        Self {
+            access_list: estimate.access_list,
+            estimated_fee_wei: estimate.fee_without_access_list,
+            estimated_fee_with_access_list_wei: estimate.fee_with_access_list,
+            error: None,
+            fee_data,
+            validation_error: None,
+        }
+    }
+
+    pub fn error(error: Error) -> Self {
+        Self {
+            access_list: vec![],
+            estimated_fee_wei: U256::zero(),
+            estimated_fee_with_access_list_wei: U256::zero(),
+            error: Some(error.to_string()),
+            fee_data: None,
+            validation_error: error.validation_error().cloned(),
+        }
+    }
+}
+
+/// Handler for access list generation endpoint.
+pub async fn handler(
+    State(estimator): State<Estimator<OverridableCollector<AnyCollector>, AnyStateProvider>>,
+    Json(request): Json<AccessListRequest>,
+) -> (StatusCode, Json<AccessListResponse>) {
+    match estimator
+        .create_access_list(request.transaction, request.state_snapshot)
+        .await
+    {
+        Ok((estimate, fee_data)) => (
+            StatusCode::OK,
+            Json(AccessListResponse::success(estimate, fee_data)),
+        ),
+        Err(error) => (error_status(&error), Json(AccessListResponse::error(error))),
+    }
+}
@@ -0,0 +1,84 @@
+use alloy::json_abi::JsonAbi;
+use axum::{extract::State, http::StatusCode, Json};
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fee_estimator::{
+        bundle::{BundleEstimate, BundleTransactionEstimate},
+        staleness::FeeDataInfo,
+        validation::ValidationError,
+        Error, Estimator, Transaction,
+    },
+    gas_price_collector::{any::AnyCollector, manual_override::OverridableCollector},
+    state::any::AnyStateProvider,
+};
+
+use super::error_status;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleRequest {
+    /// Transactions of the same sender in execution order
+    transactions: Vec<Transaction>,
+    /// Contract ABI used to decode custom errors in case a transaction reverts
+    #[serde(default)]
+    abi: Option<JsonAbi>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleResponse {
+    transactions: Vec<BundleTransactionEstimate>,
+    total_fee_wei: U256,
+    error: Option<String>,
+    #[serde(flatten)]
+    fee_data: Option<FeeDataInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failed_transaction_index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validation_error: Option<ValidationError>,
+}
+
+impl BundleResponse {
+    pub fn success(estimate: BundleEstimate, fee_data: Option<FeeDataInfo>) -> Self {
+        Self {
+            transactions: estimate.transactions,
+            total_fee_wei: estimate.total_fee,
+            error: None,
+            fee_data,
+            failed_transaction_index: None,
+            validation_error: None,
+        }
+    }
+
+    pub fn error(error: Error) -> Self {
+        let failed_transaction_index = match &error {
+            Error::BundleTransactionFailed { index, .. } => Some(*index),
+            _ => None,
+        };
+        Self {
+            transactions: vec![],
+            total_fee_wei: U256::zero(),
+            error: Some(error.to_string()),
+            fee_data: None,
+            failed_transaction_index,
+            validation_error: error.validation_error().cloned(),
+        }
+    }
+}
+
+/// Handler for bundle estimation endpoint.
+pub async fn handler(
+    State(estimator): State<Estimator<OverridableCollector<AnyCollector>, AnyStateProvider>>,
+    Json(request): Json<BundleRequest>,
+) -> (StatusCode, Json<BundleResponse>) {
+    match estimator
+        .estimate_bundle(&request.transactions, request.abi.as_ref())
+        .await
+    {
+        Ok((estimate, fee_data)) => (
+            StatusCode::OK,
+            Json(BundleResponse::success(estimate, fee_data)),
+        ),
+        Err(error) => (error_status(&error), Json(BundleResponse::error(error))),
+    }
+}
@@ -0,0 +1,121 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::gas_price_collector::{
+    any::AnyCollector,
+    manual_override::{OverridableCollector, PinnedFees},
+    static_fees::StaticFees,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeeOverrideRequest {
+    #[serde(flatten)]
+    fees: StaticFees,
+    /// How long the fees are served instead of the collected ones
+    duration_seconds: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeeOverrideResponse {
+    /// The active (or removed) override, `null` if there is none
+    pinned: Option<PinnedFees>,
+}
+
+/// Handler for returning the active fee override.
+pub async fn get_handler(
+    State(collector): State<Arc<OverridableCollector<AnyCollector>>>,
+) -> (StatusCode, Json<FeeOverrideResponse>) {
+    let pinned = collector.pinned().await;
+    (StatusCode::OK, Json(FeeOverrideResponse { pinned }))
+}
+
+/// Handler for pinning the fees served instead of the collected ones for a period of time.
+pub async fn pin_handler(
+    State(collector): State<Arc<OverridableCollector<AnyCollector>>>,
+    Json(request): Json<FeeOverrideRequest>,
+) -> (StatusCode, Json<FeeOverrideResponse>) {
+    let pinned = collector
+        .pin(request.fees, Duration::from_secs(request.duration_seconds))
+        .await;
+    (
+        StatusCode::OK,
+        Json(FeeOverrideResponse {
+            pinned: Some(pinned),
+        }),
+    )
+}
+
+/// Handler for removing the fee override, the collected fees are served again.
+pub async fn unpin_handler(
+    State(collector): State<Arc<OverridableCollector<AnyCollector>>>,
+) -> (StatusCode, Json<FeeOverrideResponse>) {
+    let pinned = collector.unpin().await;
+    (StatusCode::OK, Json(FeeOverrideResponse { pinned }))
+}
+
+#[cfg(test)]
+mod tests {
+    use primitive_types::U256;
+    use serde_json::json;
+
+    use super::*;
+    use crate::gas_price_collector::{static_fees::StaticCollector, Collector};
+
+    fn collector() -> Arc<OverridableCollector<AnyCollector>> {
+        Arc::new(OverridableCollector::new(AnyCollector::Static(
+            StaticCollector::new(StaticFees {
+                block_number: Some(U256::from(100)),
+                base_fee_per_gas: U256::from(10),
+                max_priority_fee_per_gas: U256::zero(),
+                gas_price: None,
+                blob_base_fee: None,
+            }),
+        )))
+    }
+
+    #[tokio::test]
+    async fn pins_reports_and_removes_the_override() {
+        let collector = collector();
+        let request = serde_json::from_value(json!({
+            "base_fee_per_gas": "0x14",
+            "max_priority_fee_per_gas": "0x1",
+            "duration_seconds": 60
+        }))
+        .unwrap();
+
+        let (status, Json(response)) = pin_handler(State(collector.clone()), Json(request)).await;
+        assert_eq!(status, StatusCode::OK);
+        let pinned = response.pinned.unwrap();
+        assert_eq!(pinned.fees.base_fee_per_gas, U256::from(20));
+        assert_eq!(pinned.fees.block_number, Some(U256::from(100)));
+        assert_eq!(
+            collector.fee_snapshot().await.unwrap().base_fee_per_gas,
+            U256::from(20)
+        );
+
+        let (status, Json(response)) = get_handler(State(collector.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.pinned.unwrap().expires_at, pinned.expires_at);
+
+        let (status, Json(response)) = unpin_handler(State(collector.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(response.pinned.is_some());
+        assert_eq!(
+            collector.fee_snapshot().await.unwrap().base_fee_per_gas,
+            U256::from(10)
+        );
+
+        let (_, Json(response)) = get_handler(State(collector.clone())).await;
+        assert!(response.pinned.is_none());
+        let (_, Json(response)) = unpin_handler(State(collector)).await;
+        assert!(response.pinned.is_none());
+    }
+
+    #[test]
+    fn response_serializes_missing_override_as_null() {
+        let response = serde_json::to_value(FeeOverrideResponse { pinned: None }).unwrap();
+        assert_eq!(response, json!({"pinned": null}));
+    }
+}
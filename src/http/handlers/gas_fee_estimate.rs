@@ -1,44 +1,246 @@
+use alloy::json_abi::JsonAbi;
 use axum::{extract::State, http::StatusCode, Json};
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    fee_estimator::{Estimator, Transaction},
-    gas_price_collector::in_memory::InMemoryCollector,
+    fee_estimator::{
+        affordability::Affordability,
+        simulation::SimulationOutcome,
+        staleness::FeeDataInfo,
+        validation::{ValidationError, ValidationErrorReason},
+        Error, Estimator, Transaction,
+    },
+    gas_price_collector::{any::AnyCollector, manual_override::OverridableCollector, HistoryPoint},
+    state::any::AnyStateProvider,
 };
 
+use super::error_status;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EstimateRequest {
+    #[serde(flatten)]
+    transaction: Transaction,
+    /// Execute the transaction locally and report whether it would succeed next to the estimate
+    #[serde(default)]
+    simulate: bool,
+    /// Execute the transaction locally and include its trace in the response (implies `simulate`)
+    #[serde(default)]
+    include_trace: bool,
+    /// Contract ABI used to decode custom errors in case the transaction reverts
+    #[serde(default)]
+    abi: Option<JsonAbi>,
+    /// Estimate with the gas price collected at this block instead of the current one
+    #[serde(default)]
+    block_number: Option<U256>,
+    /// Estimate with the gas price collected at this unix timestamp (in seconds) instead of the current one
+    #[serde(default)]
+    timestamp: Option<U256>,
+}
+
+impl EstimateRequest {
+    fn history_point(&self) -> Result<Option<HistoryPoint>, ValidationError> {
+        let history_point = match (self.block_number, self.timestamp) {
+            (Some(_), Some(_)) => {
+                return Err(ValidationError::new(
+                    "timestamp",
+                    ValidationErrorReason::MutuallyExclusive,
+                ))
+            }
+            (Some(block_number), None) => Some(HistoryPoint::BlockNumber(block_number)),
+            (None, Some(timestamp)) => Some(HistoryPoint::Timestamp(timestamp)),
+            (None, None) => None,
+        };
+        // only the latest state is available, so historical estimates can't be executed locally
+        if history_point.is_some() && self.simulates() {
+            return Err(ValidationError::new(
+                "simulate",
+                ValidationErrorReason::MutuallyExclusive,
+            ));
+        }
+        Ok(history_point)
+    }
+
+    fn simulates(&self) -> bool {
+        self.simulate || self.include_trace
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EstimateResponse {
     estimated_fee_wei: U256,
     error: Option<String>,
+    #[serde(flatten)]
+    fee_data: Option<FeeDataInfo>,
+    /// Set only if the transaction is executed locally
+    #[serde(skip_serializing_if = "Option::is_none")]
+    simulation: Option<SimulationOutcome>,
+    /// Set only if the sender (`from`) of the transaction is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    affordability: Option<Affordability>,
+    /// Set only if the affordability check failed (e.g. the balance could not be fetched)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    affordability_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validation_error: Option<ValidationError>,
 }
 
 impl EstimateResponse {
-    pub fn success(estimated_fee_wei: U256) -> Self {
+    pub fn success(
+        estimated_fee_wei: U256,
+        fee_data: Option<FeeDataInfo>,
+        simulation: Option<SimulationOutcome>,
+        affordability: Option<Affordability>,
+    ) -> Self {
         Self {
             estimated_fee_wei,
             error: None,
+            fee_data,
+            simulation,
+            affordability,
+            affordability_error: None,
+            validation_error: None,
         }
     }
 
-    pub fn error(error: String) -> Self {
+    pub fn error(error: Error) -> Self {
         Self {
             estimated_fee_wei: U256::zero(),
-            error: Some(error),
+            error: Some(error.to_string()),
+            fee_data: None,
+            simulation: None,
+            affordability: None,
+            affordability_error: None,
+            validation_error: error.validation_error().cloned(),
         }
     }
 }
 
 /// Handler for gas fee estimation endpoint.
 pub async fn handler(
-    State(estimator): State<Estimator<InMemoryCollector>>,
-    Json(transaction): Json<Transaction>,
+    State(estimator): State<Estimator<OverridableCollector<AnyCollector>, AnyStateProvider>>,
+    Json(request): Json<EstimateRequest>,
 ) -> (StatusCode, Json<EstimateResponse>) {
-    (
-        StatusCode::OK,
-        Json(match estimator.estimate(transaction).await {
-            Ok(fee) => EstimateResponse::success(fee),
-            Err(error) => EstimateResponse::error(error.to_string()),
-        }),
-    )
+    let history_point = match request.history_point() {
+        Ok(history_point) => history_point,
+        Err(error) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(EstimateResponse::error(error.into())),
+            )
+        }
+    };
+
+    let simulation = if request.simulates() {
+        match estimator
+            .simulate(
+                &request.transaction,
+                request.include_trace,
+                request.abi.as_ref(),
+            )
+            .await
+        {
+            Ok(simulation) => Some(simulation),
+            Err(error) => return (error_status(&error), Json(EstimateResponse::error(error))),
+        }
+    } else {
+        None
+    };
+
+    // only the latest state is available, so the balance is not checked for historical estimates,
+    // a failed check doesn't fail the estimate
+    let affordability = if history_point.is_none() {
+        estimator.affordability(&request.transaction).await
+    } else {
+        Ok(None)
+    };
+
+    let (fee, fee_data) = match estimator
+        .estimate_at(request.transaction, history_point)
+        .await
+    {
+        Ok(estimate) => estimate,
+        Err(error) => return (error_status(&error), Json(EstimateResponse::error(error))),
+    };
+
+    let response = match affordability {
+        Ok(affordability) => EstimateResponse::success(fee, fee_data, simulation, affordability),
+        Err(error) => EstimateResponse {
+            affordability_error: Some(error.to_string()),
+            ..EstimateResponse::success(fee, fee_data, simulation, None)
+        },
+    };
+    (StatusCode::OK, Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use alloy::rpc::client::ClientBuilder;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        fee_estimator::staleness::StalenessPolicy,
+        gas_price_collector::static_fees::{StaticCollector, StaticFees},
+        simulator::Simulator,
+        state::rpc::RpcStateProvider,
+        test_utils::StubRpcServer,
+    };
+
+    #[tokio::test]
+    async fn failed_balance_lookup_keeps_the_estimate() {
+        // the latest block is known, but balances can't be fetched
+        let server = StubRpcServer::start(|method, _| match method {
+            "eth_getBlockByNumber" => Some(json!({
+                "number": "0x64",
+                "timestamp": "0x67e2a1bb",
+                "miner": "0x0000000000000000000000000000000000000000",
+                "gasLimit": "0x1c9c380"
+            })),
+            "eth_chainId" => Some(json!("0x1")),
+            _ => None,
+        })
+        .await;
+        let state_provider =
+            RpcStateProvider::new(ClientBuilder::default().http(server.url.clone()));
+        let estimator = Estimator::new(
+            Arc::new(OverridableCollector::new(AnyCollector::Static(
+                StaticCollector::new(StaticFees {
+                    block_number: Some(U256::from(100)),
+                    base_fee_per_gas: U256::from(1_000_000_000),
+                    max_priority_fee_per_gas: U256::zero(),
+                    gas_price: None,
+                    blob_base_fee: None,
+                }),
+            ))),
+            Arc::new(Simulator::new(Arc::new(AnyStateProvider::Rpc(
+                state_provider,
+            )))),
+            StalenessPolicy {
+                max_age: Duration::from_secs(60),
+                allow_stale: false,
+            },
+        );
+        let request = serde_json::from_value(json!({
+            "Legacy": {
+                "gas_price": "0x2",
+                "gas_limit": "0x30d40",
+                "input": "",
+                "action": {"Call": "0x0101010101010101010101010101010101010101"},
+                "from": "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+            }
+        }))
+        .unwrap();
+
+        let (status, Json(response)) = handler(State(estimator), Json(request)).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.error, None);
+        assert!(!response.estimated_fee_wei.is_zero());
+        assert!(response.affordability.is_none());
+        assert!(response.affordability_error.is_some());
+        assert_eq!(server.count("eth_getBalance"), 1);
+    }
 }
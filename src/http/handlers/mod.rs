@@ -1 +1,56 @@
+use axum::http::StatusCode;
+
+use crate::fee_estimator::Error;
+
+pub mod access_list;
+pub mod bundle;
+pub mod fee_override;
 pub mod gas_fee_estimate;
+pub mod readiness;
+pub mod rpc_usage;
+pub mod transaction_type_comparison;
+
+/// Returns the status code of a failed request, invalid requests are rejected with `400 Bad Request`.
+fn error_status(error: &Error) -> StatusCode {
+    if error.is_invalid_request() {
+        StatusCode::BAD_REQUEST
+    } else {
+        StatusCode::OK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fee_estimator::validation::{ValidationError, ValidationErrorReason};
+
+    use super::*;
+
+    #[test]
+    fn invalid_requests_are_rejected() {
+        let validation_error = ValidationError::new("gas_limit", ValidationErrorReason::TooLarge);
+        assert_eq!(
+            error_status(&validation_error.clone().into()),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            error_status(&Error::BundleTransactionFailed {
+                index: 1,
+                error: Box::new(validation_error.into()),
+            }),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(error_status(&Error::EmptyBundle), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn estimation_failures_are_not_rejected() {
+        assert_eq!(error_status(&Error::GasPriceNotReady), StatusCode::OK);
+        assert_eq!(
+            error_status(&Error::StaleFeeData {
+                age: 120,
+                max_age: 60
+            }),
+            StatusCode::OK
+        );
+    }
+}
@@ -0,0 +1,26 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fee_estimator::Estimator,
+    gas_price_collector::{any::AnyCollector, manual_override::OverridableCollector},
+    state::any::AnyStateProvider,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    ready: bool,
+}
+
+/// Handler for readiness endpoint, it is ready when estimates can use the current gas price.
+pub async fn handler(
+    State(estimator): State<Estimator<OverridableCollector<AnyCollector>, AnyStateProvider>>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let ready = estimator.is_ready().await;
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(ReadinessResponse { ready }))
+}
@@ -0,0 +1,10 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Json};
+
+use crate::rpc_budget::{RpcBudget, RpcUsage};
+
+/// Handler for JSON RPC usage endpoint, returns the requests sent to the ethereum clients today.
+pub async fn handler(State(budget): State<Arc<RpcBudget>>) -> (StatusCode, Json<RpcUsage>) {
+    (StatusCode::OK, Json(budget.usage()))
+}
@@ -0,0 +1,68 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fee_estimator::{
+        comparison::{TransactionIntent, TransactionType, TransactionTypeComparison},
+        staleness::FeeDataInfo,
+        validation::ValidationError,
+        Error, Estimator,
+    },
+    gas_price_collector::{any::AnyCollector, manual_override::OverridableCollector},
+    state::any::AnyStateProvider,
+};
+
+use super::error_status;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionTypeComparisonResponse {
+    estimated_fees_wei: Option<TransactionTypeComparison>,
+    cheapest: Option<TransactionType>,
+    error: Option<String>,
+    #[serde(flatten)]
+    fee_data: Option<FeeDataInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validation_error: Option<ValidationError>,
+}
+
+impl TransactionTypeComparisonResponse {
+    pub fn success(comparison: TransactionTypeComparison, fee_data: Option<FeeDataInfo>) -> Self {
+        Self {
+            cheapest: Some(comparison.cheapest()),
+            estimated_fees_wei: Some(comparison),
+            error: None,
+            fee_data,
+            validation_error: None,
+        }
+    }
+
+    pub fn error(error: Error) -> Self {
+        Self {
+            estimated_fees_wei: None,
+            cheapest: None,
+            error: Some(error.to_string()),
+            fee_data: None,
+            validation_error: error.validation_error().cloned(),
+        }
+    }
+}
+
+/// Handler for transaction type comparison endpoint.
+pub async fn handler(
+    State(estimator): State<Estimator<OverridableCollector<AnyCollector>, AnyStateProvider>>,
+    Json(intent): Json<TransactionIntent>,
+) -> (StatusCode, Json<TransactionTypeComparisonResponse>) {
+    match estimator.compare_transaction_types(intent).await {
+        Ok((comparison, fee_data)) => (
+            StatusCode::OK,
+            Json(TransactionTypeComparisonResponse::success(
+                comparison,
+                Some(fee_data),
+            )),
+        ),
+        Err(error) => (
+            error_status(&error),
+            Json(TransactionTypeComparisonResponse::error(error)),
+        ),
+    }
+}
@@ -1,15 +1,38 @@
 use std::{io, sync::Arc, time::Duration};
 
-use axum::{routing::post, Router};
+use axum::{
+    routing::{get, post},
+    Router,
+};
 use log::{error, info};
 use thiserror::Error;
 use tokio::task::{JoinError, JoinHandle};
 use tokio_util::sync::CancellationToken;
-use tower_http::timeout::TimeoutLayer;
+use tower_http::{timeout::TimeoutLayer, validate_request::ValidateRequestHeaderLayer};
 
 use crate::{
-    fee_estimator::Estimator,
-    gas_price_collector::{self, in_memory::InMemoryCollector, Collector},
+    fee_estimator::{staleness::StalenessPolicy, Estimator},
+    gas_price_collector::{
+        self,
+        any::AnyCollector,
+        endpoints::RpcEndpoints,
+        in_memory::InMemoryCollector,
+        local_node::LocalNodeCollector,
+        manual_override::OverridableCollector,
+        on_demand::OnDemandCollector,
+        polling::PollingConfig,
+        replay::ReplayCollector,
+        static_fees::{StaticCollector, StaticFees},
+        storage::{
+            self as gas_price_storage, any::AnyGasPriceStorage, file::FileStorage,
+            ring_buffer::RingBufferStorage,
+        },
+        websocket::WebSocketCollector,
+        Collector,
+    },
+    rpc_budget::RpcBudget,
+    simulator::Simulator,
+    state::{self, any::AnyStateProvider, rpc::RpcStateProvider, snapshot::SnapshotStateProvider},
     Cli,
 };
 
@@ -23,8 +46,18 @@ pub enum Error {
     IO(#[from] io::Error),
     #[error("Task join error: {0}")]
     TaskJoin(#[from] JoinError),
-    #[error("In-memory gas price collector error: {0}")]
-    InMemoryCollector(#[from] gas_price_collector::in_memory::Error),
+    #[error("Ethereum client JSON-RPC endpoint error: {0}")]
+    Endpoints(#[from] gas_price_collector::endpoints::Error),
+    #[error("Gas price collector error: {0}")]
+    Collector(#[from] gas_price_collector::any::Error),
+    #[error("Static fees error: {0}")]
+    StaticFees(#[from] gas_price_collector::static_fees::Error),
+    #[error("Fee history replay error: {0}")]
+    Replay(#[from] gas_price_collector::replay::Error),
+    #[error("State snapshot error: {0}")]
+    StateSnapshot(#[from] state::snapshot::Error),
+    #[error("Gas price storage error: {0}")]
+    GasPriceStorage(#[from] gas_price_storage::file::Error),
 }
 
 /// The main server struct that manages HTTP server and it's related services.
@@ -43,23 +76,72 @@ impl HttpServer {
 
     /// Starts the HTTP server and all of its needed services.
     pub async fn start(&mut self, cli: &Cli) -> Result<(), Error> {
-        let gas_price_collector =
-            Arc::new(InMemoryCollector::new(cli.eth_json_rpc_client_url.clone()));
+        let rpc_budget = Arc::new(RpcBudget::new(cli.daily_rpc_budget));
+        let state_snapshot = cli
+            .state_snapshot
+            .as_deref()
+            .map(SnapshotStateProvider::load)
+            .transpose()?;
+        let gas_price_collector = Arc::new(OverridableCollector::new(
+            Self::collector(cli, rpc_budget.clone(), state_snapshot.as_ref()).await?,
+        ));
 
         // start collector
         let collector = gas_price_collector.clone();
         let cancel_token = self.cancel_token.clone();
         self.sub_tasks.push(tokio::spawn(async move {
-            collector.start(cancel_token).await?;
+            if let Err(error) = collector.start(cancel_token).await {
+                error!(target: LOG_TARGET, "Gas price collector stopped: {error:?}");
+                return Err(error.into());
+            }
             Ok(())
         }));
 
+        // local transaction execution
+        let state_provider = match state_snapshot {
+            Some(state_snapshot) => AnyStateProvider::Snapshot(state_snapshot),
+            None => {
+                // static and replay collectors don't connect to any Ethereum client
+                let eth_client = match gas_price_collector.inner().eth_client() {
+                    Some(eth_client) => eth_client,
+                    None => Self::endpoints(cli, rpc_budget.clone())?.primary_client(),
+                };
+                AnyStateProvider::Rpc(RpcStateProvider::new(eth_client))
+            }
+        };
+        let simulator = Arc::new(Simulator::new(Arc::new(state_provider)));
+
         // http server
-        let estimator = Estimator::new(gas_price_collector);
-        let app = Router::new()
+        let staleness_policy = StalenessPolicy {
+            max_age: Duration::from_secs(cli.max_fee_data_age),
+            allow_stale: cli.allow_stale_fee_data,
+        };
+        let estimator = Estimator::new(gas_price_collector.clone(), simulator, staleness_policy);
+        let mut app = Router::new()
             .route("/estimate", post(handlers::gas_fee_estimate::handler))
-            .with_state(estimator)
-            .layer((TimeoutLayer::new(Duration::from_secs(10)),));
+            .route("/access_list", post(handlers::access_list::handler))
+            .route("/estimate_bundle", post(handlers::bundle::handler))
+            .route("/ready", get(handlers::readiness::handler))
+            .route(
+                "/rpc_usage",
+                get(handlers::rpc_usage::handler).with_state(rpc_budget),
+            )
+            .route(
+                "/compare_transaction_types",
+                post(handlers::transaction_type_comparison::handler),
+            )
+            .with_state(estimator);
+        if let Some(admin_token) = &cli.admin_token {
+            app = app.route(
+                "/admin/fee_override",
+                get(handlers::fee_override::get_handler)
+                    .post(handlers::fee_override::pin_handler)
+                    .delete(handlers::fee_override::unpin_handler)
+                    .route_layer(ValidateRequestHeaderLayer::bearer(admin_token))
+                    .with_state(gas_price_collector),
+            );
+        }
+        let app = app.layer((TimeoutLayer::new(Duration::from_secs(10)),));
         let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", cli.port)).await?;
 
         info!(target: LOG_TARGET, "Starting HTTP server at http://127.0.0.1:{}", cli.port);
@@ -75,6 +157,105 @@ impl HttpServer {
         Ok(())
     }
 
+    /// Creates the gas price collector selected through the CLI arguments.
+    /// Without any Ethereum client or fee source the fees of the state snapshot's block are served,
+    /// so no external calls are made at all.
+    async fn collector(
+        cli: &Cli,
+        rpc_budget: Arc<RpcBudget>,
+        state_snapshot: Option<&SnapshotStateProvider>,
+    ) -> Result<AnyCollector, Error> {
+        if let Some(path) = &cli.static_fees_file {
+            return Ok(AnyCollector::Static(StaticCollector::load(path)?));
+        }
+        if let Some(base_fee_per_gas) = cli.static_base_fee {
+            return Ok(AnyCollector::Static(StaticCollector::new(StaticFees {
+                block_number: None,
+                base_fee_per_gas: base_fee_per_gas.into(),
+                max_priority_fee_per_gas: cli.static_priority_fee.unwrap_or_default().into(),
+                gas_price: None,
+                blob_base_fee: cli.static_blob_base_fee.map(Into::into),
+            })));
+        }
+        let offline = cli.eth_json_rpc_client_url.is_empty()
+            && cli.local_node.is_none()
+            && cli.replay_file.is_none();
+        if let Some(state_snapshot) = state_snapshot.filter(|_| offline) {
+            let environment = &state_snapshot.snapshot().environment;
+            return Ok(AnyCollector::Static(StaticCollector::new(StaticFees {
+                block_number: Some(environment.number),
+                base_fee_per_gas: environment.base_fee_per_gas,
+                max_priority_fee_per_gas: Default::default(),
+                gas_price: None,
+                blob_base_fee: None,
+            })));
+        }
+
+        if let Some(path) = &cli.replay_file {
+            return Ok(AnyCollector::Replay(ReplayCollector::load(
+                path,
+                cli.replay_speed,
+            )?));
+        }
+        let gas_price_storage = match &cli.gas_price_history_file {
+            Some(path) => AnyGasPriceStorage::File(
+                FileStorage::open(path, gas_price_storage::DEFAULT_CAPACITY).await?,
+            ),
+            None => AnyGasPriceStorage::RingBuffer(RingBufferStorage::new(
+                gas_price_storage::DEFAULT_CAPACITY,
+            )),
+        };
+        let gas_price_storage = Arc::new(gas_price_storage);
+        let max_stale_age = cli.max_stale_gas_price_age.map(Duration::from_secs);
+        let polling = PollingConfig {
+            interval: Duration::from_secs(cli.poll_interval),
+            min_interval: Duration::from_secs(cli.min_poll_interval),
+            failure_threshold: cli.circuit_breaker_threshold,
+            cooldown: Duration::from_secs(cli.circuit_breaker_cooldown),
+        };
+        Ok(
+            match (&cli.local_node, &cli.eth_ws_url, cli.on_demand_ttl) {
+                (Some(node), _, _) => AnyCollector::LocalNode(LocalNodeCollector::new(
+                    node,
+                    rpc_budget,
+                    gas_price_storage,
+                    max_stale_age,
+                    polling,
+                )),
+                (None, Some(ws_url), _) => AnyCollector::WebSocket(WebSocketCollector::new(
+                    ws_url.clone(),
+                    Self::endpoints(cli, rpc_budget)?,
+                    gas_price_storage,
+                    max_stale_age,
+                    polling,
+                )),
+                (None, None, Some(ttl)) => AnyCollector::OnDemand(OnDemandCollector::new(
+                    Self::endpoints(cli, rpc_budget)?,
+                    gas_price_storage,
+                    max_stale_age,
+                    Duration::from_secs(ttl),
+                    polling,
+                )),
+                (None, None, None) => AnyCollector::InMemory(InMemoryCollector::new(
+                    Self::endpoints(cli, rpc_budget)?,
+                    gas_price_storage,
+                    max_stale_age,
+                    polling,
+                )),
+            },
+        )
+    }
+
+    fn endpoints(cli: &Cli, rpc_budget: Arc<RpcBudget>) -> Result<RpcEndpoints, Error> {
+        Ok(RpcEndpoints::new(
+            &cli.eth_json_rpc_client_url,
+            cli.endpoint_strategy,
+            cli.max_block_lag,
+            cli.min_quorum,
+            rpc_budget,
+        )?)
+    }
+
     async fn shutdown_signal(cancel_token: CancellationToken) {
         cancel_token.cancelled().await
     }
@@ -1,11 +1,18 @@
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use fee_estimator::staleness::DEFAULT_MAX_FEE_DATA_AGE;
+use gas_price_collector::{endpoints::EndpointStrategy, local_node::LocalNode};
 use http::server::HttpServer;
 use simple_logger::SimpleLogger;
-use std::sync::mpsc::channel;
+use std::{path::PathBuf, sync::mpsc::channel};
 
 mod fee_estimator;
 mod gas_price_collector;
 mod http;
+mod rpc_budget;
+mod simulator;
+mod state;
+#[cfg(test)]
+mod test_utils;
 
 #[derive(Parser)]
 #[command(version, about)]
@@ -14,10 +21,149 @@ pub struct Cli {
     #[arg(short = 'p', long, default_value_t = 9999)]
     port: u16,
 
-    /// Ethereum client JSON-RPC URL.
-    /// Example: https://mainnet.infura.io/v3/<YOUR_API_KEY>
-    #[arg(short = 'u', long)]
-    eth_json_rpc_client_url: url::Url,
+    /// Ethereum client JSON-RPC URL, can be set multiple times (or comma separated) to use multiple endpoints.
+    /// Transactions are executed on its state, so it is required with static or replayed fees too, unless
+    /// `--state-snapshot` is set. Example: https://mainnet.infura.io/v3/<YOUR_API_KEY>
+    #[arg(
+        short = 'u',
+        long,
+        required_unless_present_any = ["local_node", "state_snapshot"],
+        value_delimiter = ','
+    )]
+    eth_json_rpc_client_url: Vec<url::Url>,
+
+    /// Ethereum client WebSocket JSON-RPC URL, if set fee data is updated on every new block through a `newHeads`
+    /// subscription and the JSON-RPC URLs are polled only while the WebSocket connection is down.
+    /// Example: wss://mainnet.infura.io/ws/v3/<YOUR_API_KEY>
+    #[arg(long)]
+    eth_ws_url: Option<url::Url>,
+
+    /// Local Ethereum node IPC socket path or plain HTTP JSON-RPC URL, if set fee data is collected from this node
+    /// (and transactions are executed on its state) instead of the Ethereum client JSON-RPC URLs.
+    /// Example: /root/.ethereum/geth.ipc
+    #[arg(long, value_name = "IPC_PATH_OR_URL")]
+    local_node: Option<LocalNode>,
+
+    /// Fetch fee data only when an estimate needs it and the last fetched one is older than this many seconds,
+    /// instead of polling it continuously (nothing is fetched while there is no traffic).
+    #[arg(long, value_name = "TTL_SECONDS", conflicts_with_all = ["eth_ws_url", "local_node"])]
+    on_demand_ttl: Option<u64>,
+
+    /// Serve this base fee / gas (in WEI) instead of collecting fee data from an Ethereum client,
+    /// e.g. for offline development (use it with `--state-snapshot` to run without any Ethereum client).
+    #[arg(long, value_name = "WEI", conflicts_with_all = ["eth_ws_url", "local_node", "on_demand_ttl"])]
+    static_base_fee: Option<u128>,
+
+    /// Priority fee / gas in WEI served with `--static-base-fee` [default: 0]
+    #[arg(long, value_name = "WEI", requires = "static_base_fee")]
+    static_priority_fee: Option<u128>,
+
+    /// Blob base fee / gas in WEI served with `--static-base-fee`.
+    #[arg(long, value_name = "WEI", requires = "static_base_fee")]
+    static_blob_base_fee: Option<u128>,
+
+    /// JSON file with the fee values to serve instead of collecting fee data from an Ethereum client.
+    #[arg(long, conflicts_with_all = ["static_base_fee", "eth_ws_url", "local_node", "on_demand_ttl"])]
+    static_fees_file: Option<PathBuf>,
+
+    /// Fee history file (JSON lines or CSV) to replay instead of collecting fee data from an Ethereum client,
+    /// e.g. a `--gas-price-history-file` recorded earlier. Replayed fees are kept in memory only.
+    #[arg(long, conflicts_with_all = ["static_base_fee", "static_fees_file", "eth_ws_url", "local_node", "on_demand_ttl", "gas_price_history_file"])]
+    replay_file: Option<PathBuf>,
+
+    /// Speed factor of replaying the fee history, e.g. 60 replays an hour of fee history in a minute.
+    #[arg(long, default_value_t = 1.0)]
+    replay_speed: f64,
+
+    /// How fee data is fetched when multiple Ethereum client JSON-RPC URLs are set.
+    #[arg(long, value_enum, default_value_t = EndpointStrategy::Failover)]
+    endpoint_strategy: EndpointStrategy,
+
+    /// Maximum number of blocks an endpoint can be behind the others to be used in quorum mode.
+    #[arg(long, default_value_t = 2)]
+    max_block_lag: u64,
+
+    /// Minimum number of endpoints that must return usable fee data in quorum mode,
+    /// a majority of the endpoints if not set.
+    #[arg(long)]
+    min_quorum: Option<usize>,
+
+    /// JSON state snapshot file, if set transactions are executed locally on this state
+    /// instead of the state fetched from the Ethereum client.
+    /// Without `-u` (and any other fee source) the base fee of the snapshot's block is served, so nothing is fetched.
+    #[arg(long)]
+    state_snapshot: Option<PathBuf>,
+
+    /// File where every collected gas price is recorded and reloaded from on startup,
+    /// if not set the gas price history is kept in memory only.
+    #[arg(long)]
+    gas_price_history_file: Option<PathBuf>,
+
+    /// Maximum age in seconds of the last stored gas price to be used for estimation until a fresh one is collected,
+    /// if not set estimates that need the current gas price are refused until then.
+    /// Can't be greater than `--max-fee-data-age` unless `--allow-stale-fee-data` is set.
+    #[arg(long)]
+    max_stale_gas_price_age: Option<u64>,
+
+    /// Maximum interval in seconds between two polls of fee data, it is shortened to the observed block time.
+    #[arg(long, default_value_t = 30)]
+    poll_interval: u64,
+
+    /// Minimum interval in seconds between two polls of fee data, also the initial retry delay on failures.
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+    min_poll_interval: u64,
+
+    /// Number of consecutive polling failures after which polling is suspended.
+    #[arg(long, default_value_t = 5)]
+    circuit_breaker_threshold: u32,
+
+    /// Duration in seconds of suspending polling after too many failures, also the maximum retry delay.
+    #[arg(long, default_value_t = 300)]
+    circuit_breaker_cooldown: u64,
+
+    /// Maximum number of JSON-RPC requests per (UTC) day, fee data polling is slowed down
+    /// to spread the remaining requests evenly over the rest of the day.
+    /// If not set, requests are only counted.
+    #[arg(long)]
+    daily_rpc_budget: Option<u64>,
+
+    /// Maximum age in seconds of the collected fee data, estimates based on older fee data
+    /// (e.g. because the Ethereum client is down) are refused.
+    #[arg(long, default_value_t = DEFAULT_MAX_FEE_DATA_AGE.as_secs())]
+    max_fee_data_age: u64,
+
+    /// Estimate with fee data older than `--max-fee-data-age` with a warning instead of refusing it.
+    #[arg(long)]
+    allow_stale_fee_data: bool,
+
+    /// Token required by the admin endpoints (as `Authorization: Bearer <TOKEN>` header),
+    /// the admin endpoints are disabled if not set.
+    #[arg(long, env = "ADMIN_TOKEN")]
+    admin_token: Option<String>,
+}
+
+impl Cli {
+    /// Checks the constraints between the arguments that can't be expressed through clap attributes.
+    fn validate(self) -> Result<Self, clap::Error> {
+        if self.min_poll_interval > self.poll_interval {
+            return Err(Cli::command().error(
+                ErrorKind::ArgumentConflict,
+                "--min-poll-interval can't be greater than --poll-interval",
+            ));
+        }
+        // a stale gas price older than the maximum fee data age would be reported ready, but refused by the estimates
+        if !self.allow_stale_fee_data
+            && self
+                .max_stale_gas_price_age
+                .is_some_and(|max_stale_age| max_stale_age > self.max_fee_data_age)
+        {
+            return Err(Cli::command().error(
+                ErrorKind::ArgumentConflict,
+                "--max-stale-gas-price-age can't be greater than --max-fee-data-age without --allow-stale-fee-data",
+            ));
+        }
+        Ok(self)
+    }
 }
 
 #[tokio::main]
@@ -27,7 +173,7 @@ async fn main() -> anyhow::Result<()> {
         .expect("Failed to initialize logging");
     log::set_max_level(log::LevelFilter::Info);
 
-    let cli = Cli::parse();
+    let cli = Cli::parse().validate().unwrap_or_else(|error| error.exit());
 
     // termination handler
     let (term_tx, term_rx) = channel();
@@ -45,3 +191,113 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(["eth_gas_fee_estimator"].iter().chain(args)).and_then(Cli::validate)
+    }
+
+    #[test]
+    fn ethereum_client_is_required() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["-u", "http://127.0.0.1:8545"]).is_ok());
+    }
+
+    #[test]
+    fn state_snapshot_runs_without_ethereum_client() {
+        let cli = parse(&["--state-snapshot", "snapshot.json"]).unwrap();
+        assert!(cli.eth_json_rpc_client_url.is_empty());
+    }
+
+    #[test]
+    fn static_and_replayed_fees_need_a_state_source() {
+        for fee_source in [
+            ["--static-base-fee", "1000000000"],
+            ["--static-fees-file", "fees.json"],
+            ["--replay-file", "history.csv"],
+        ] {
+            let error = parse(&fee_source).err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::MissingRequiredArgument);
+            assert!(parse(&[&fee_source[..], &["-u", "http://127.0.0.1:8545"]].concat()).is_ok());
+            assert!(
+                parse(&[&fee_source[..], &["--state-snapshot", "snapshot.json"]].concat()).is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn replayed_fees_are_not_recorded() {
+        let error = parse(&[
+            "-u",
+            "http://127.0.0.1:8545",
+            "--replay-file",
+            "history.csv",
+            "--gas-price-history-file",
+            "history.jsonl",
+        ])
+        .err()
+        .unwrap();
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn poll_intervals_are_validated() {
+        let parse_intervals = |poll_interval: &str, min_poll_interval: &str| {
+            parse(&[
+                "-u",
+                "http://127.0.0.1:8545",
+                "--poll-interval",
+                poll_interval,
+                "--min-poll-interval",
+                min_poll_interval,
+            ])
+        };
+
+        assert!(parse_intervals("10", "10").is_ok());
+        assert_eq!(
+            parse_intervals("10", "0").err().unwrap().kind(),
+            ErrorKind::ValueValidation
+        );
+        assert_eq!(
+            parse_intervals("5", "10").err().unwrap().kind(),
+            ErrorKind::ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn stale_gas_price_age_is_limited_by_fee_data_age() {
+        let parse_ages = |max_stale_gas_price_age: &str, extra: &[&str]| {
+            let args = [
+                &[
+                    "-u",
+                    "http://127.0.0.1:8545",
+                    "--max-stale-gas-price-age",
+                    max_stale_gas_price_age,
+                    "--max-fee-data-age",
+                    "300",
+                ],
+                extra,
+            ]
+            .concat();
+            parse(&args)
+        };
+
+        assert!(parse_ages("300", &[]).is_ok());
+        assert_eq!(
+            parse_ages("301", &[]).err().unwrap().kind(),
+            ErrorKind::ArgumentConflict
+        );
+        assert!(parse_ages("3600", &["--allow-stale-fee-data"]).is_ok());
+        // the default maximum fee data age is checked too
+        assert!(parse(&[
+            "-u",
+            "http://127.0.0.1:8545",
+            "--max-stale-gas-price-age",
+            "3600"
+        ])
+        .is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use alloy::{
+    rpc::json_rpc::{RequestPacket, ResponsePacket},
+    transports::{TransportError, TransportFut},
+};
+use tower::{Layer, Service};
+
+use super::RpcBudget;
+
+/// Transport layer that records every JSON RPC request sent through the client in the budget.
+#[derive(Clone)]
+pub struct RpcBudgetLayer {
+    budget: Arc<RpcBudget>,
+}
+
+impl RpcBudgetLayer {
+    pub fn new(budget: Arc<RpcBudget>) -> Self {
+        Self { budget }
+    }
+}
+
+impl<S> Layer<S> for RpcBudgetLayer {
+    type Service = RpcBudgetService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcBudgetService {
+            inner,
+            budget: self.budget.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RpcBudgetService<S> {
+    inner: S,
+    budget: Arc<RpcBudget>,
+}
+
+impl<S> Service<RequestPacket> for RpcBudgetService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>
+        + Send
+        + 'static
+        + Clone,
+    S::Future: Send + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        match &request {
+            RequestPacket::Single(request) => self.budget.record(request.method()),
+            RequestPacket::Batch(requests) => requests
+                .iter()
+                .for_each(|request| self.budget.record(request.method())),
+        }
+
+        let future = self.inner.call(request);
+        Box::pin(future)
+    }
+}
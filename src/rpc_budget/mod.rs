@@ -0,0 +1,215 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use log::warn;
+use serde::Serialize;
+
+use crate::gas_price_collector::unix_timestamp;
+
+pub mod layer;
+
+const LOG_TARGET: &str = "rpc_budget";
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Accounts the JSON RPC requests sent to the ethereum clients per (UTC) day,
+/// so fee data collection can be slowed down to stay within the provider quota.
+pub struct RpcBudget {
+    /// Maximum number of requests per day, unlimited if not set
+    daily_limit: Option<u64>,
+    usage: Mutex<DailyUsage>,
+    /// Returns the current unix timestamp in seconds
+    clock: Box<dyn Fn() -> u64 + Send + Sync>,
+}
+
+#[derive(Default)]
+struct DailyUsage {
+    /// Number of days since the unix epoch
+    day: u64,
+    requests: u64,
+    by_method: BTreeMap<String, u64>,
+    exhausted: bool,
+}
+
+/// Requests sent in the current period.
+#[derive(Debug, Serialize)]
+pub struct RpcUsage {
+    /// Unix timestamp of the start of the current period (UTC midnight)
+    pub period_started_at: u64,
+    pub resets_in_seconds: u64,
+    pub requests: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_budget: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<u64>,
+    pub by_method: BTreeMap<String, u64>,
+}
+
+impl RpcBudget {
+    pub fn new(daily_limit: Option<u64>) -> Self {
+        Self::with_clock(daily_limit, unix_timestamp)
+    }
+
+    /// Creates a budget that tells the time with the given clock (returning unix timestamps in seconds).
+    fn with_clock(
+        daily_limit: Option<u64>,
+        clock: impl Fn() -> u64 + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            daily_limit,
+            usage: Mutex::new(DailyUsage::default()),
+            clock: Box::new(clock),
+        }
+    }
+
+    /// Records a request of the given JSON RPC method.
+    pub fn record(&self, method: &str) {
+        let mut usage = self.current_usage();
+        usage.requests += 1;
+        *usage.by_method.entry(method.to_string()).or_default() += 1;
+        if let Some(daily_limit) = self.daily_limit {
+            if usage.requests >= daily_limit && !usage.exhausted {
+                usage.exhausted = true;
+                warn!(target: LOG_TARGET, "Daily JSON RPC budget of {daily_limit} requests is exhausted");
+            }
+        }
+    }
+
+    /// Returns the number of requests sent in the current period.
+    pub fn requests(&self) -> u64 {
+        self.current_usage().requests
+    }
+
+    pub fn usage(&self) -> RpcUsage {
+        let usage = self.current_usage();
+        let period_started_at = usage.day * SECONDS_PER_DAY;
+        RpcUsage {
+            period_started_at,
+            resets_in_seconds: (period_started_at + SECONDS_PER_DAY).saturating_sub((self.clock)()),
+            requests: usage.requests,
+            daily_budget: self.daily_limit,
+            remaining: self
+                .daily_limit
+                .map(|daily_limit| daily_limit.saturating_sub(usage.requests)),
+            by_method: usage.by_method.clone(),
+        }
+    }
+
+    /// Returns the minimum delay between two updates that need `requests_per_update` requests,
+    /// so the remaining budget is spread evenly over the rest of the day.
+    /// The delay grows as the budget is consumed, once it is exhausted updates are delayed until the next day.
+    pub fn pacing_delay(&self, requests_per_update: u64) -> Duration {
+        let Some(daily_limit) = self.daily_limit else {
+            return Duration::ZERO;
+        };
+        let usage = self.current_usage();
+        let now = (self.clock)();
+        let seconds_left = ((usage.day + 1) * SECONDS_PER_DAY).saturating_sub(now);
+        let remaining = daily_limit.saturating_sub(usage.requests);
+        if remaining == 0 {
+            return Duration::from_secs(seconds_left);
+        }
+        let delay = seconds_left as f64 * requests_per_update.max(1) as f64 / remaining as f64;
+        Duration::from_secs_f64(delay.min(seconds_left as f64))
+    }
+
+    /// Returns the usage of the current day, it is reset when a new day is started.
+    fn current_usage(&self) -> MutexGuard<'_, DailyUsage> {
+        let mut usage = self.usage.lock().unwrap_or_else(|error| error.into_inner());
+        let day = (self.clock)() / SECONDS_PER_DAY;
+        if usage.day != day {
+            *usage = DailyUsage {
+                day,
+                ..Default::default()
+            };
+        }
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    /// Start of an arbitrary UTC day.
+    const DAY_START: u64 = 20_000 * SECONDS_PER_DAY;
+
+    /// Returns a budget with the given daily limit and the time it reads.
+    fn budget(daily_limit: Option<u64>) -> (RpcBudget, Arc<AtomicU64>) {
+        let now = Arc::new(AtomicU64::new(DAY_START));
+        let clock = now.clone();
+        let budget = RpcBudget::with_clock(daily_limit, move || clock.load(Ordering::Relaxed));
+        (budget, now)
+    }
+
+    fn record(budget: &RpcBudget, requests: u64) {
+        for _ in 0..requests {
+            budget.record("eth_gasPrice");
+        }
+    }
+
+    #[test]
+    fn unlimited_budget_is_not_paced() {
+        let (budget, _) = budget(None);
+        record(&budget, 1_000);
+
+        assert_eq!(budget.pacing_delay(3), Duration::ZERO);
+        assert_eq!(budget.usage().requests, 1_000);
+        assert_eq!(budget.usage().remaining, None);
+    }
+
+    #[test]
+    fn remaining_requests_are_spread_over_the_rest_of_the_day() {
+        let (budget, now) = budget(Some(1_000));
+
+        // 3 of 1000 requests per update over a full day
+        assert_eq!(budget.pacing_delay(3), Duration::from_secs_f64(259.2));
+        record(&budget, 500);
+        assert_eq!(budget.pacing_delay(3), Duration::from_secs_f64(518.4));
+        // half of the day left for the remaining 500 requests
+        now.store(DAY_START + SECONDS_PER_DAY / 2, Ordering::Relaxed);
+        assert_eq!(budget.pacing_delay(3), Duration::from_secs_f64(259.2));
+        // the delay doesn't exceed the rest of the day
+        now.store(DAY_START + SECONDS_PER_DAY - 100, Ordering::Relaxed);
+        record(&budget, 499);
+        assert_eq!(budget.pacing_delay(3), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn exhausted_budget_waits_for_the_next_day() {
+        let (budget, now) = budget(Some(100));
+        now.store(DAY_START + SECONDS_PER_DAY - 3_600, Ordering::Relaxed);
+        record(&budget, 150);
+
+        assert_eq!(budget.pacing_delay(3), Duration::from_secs(3_600));
+        assert_eq!(budget.usage().remaining, Some(0));
+        assert_eq!(budget.usage().resets_in_seconds, 3_600);
+    }
+
+    #[test]
+    fn usage_is_reset_on_day_rollover() {
+        let (budget, now) = budget(Some(100));
+        record(&budget, 100);
+        assert_eq!(budget.requests(), 100);
+
+        now.store(DAY_START + SECONDS_PER_DAY, Ordering::Relaxed);
+
+        assert_eq!(budget.requests(), 0);
+        let usage = budget.usage();
+        assert_eq!(usage.period_started_at, DAY_START + SECONDS_PER_DAY);
+        assert_eq!(usage.remaining, Some(100));
+        assert!(usage.by_method.is_empty());
+        assert_eq!(budget.pacing_delay(1), Duration::from_secs(864));
+        // exhaustion is reported again in the new day
+        record(&budget, 100);
+        assert!(budget.usage.lock().unwrap().exhausted);
+    }
+}
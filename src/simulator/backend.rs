@@ -0,0 +1,321 @@
+use std::{cell::RefCell, collections::HashMap, future::Future, sync::Arc};
+
+use evm::backend::{Apply, ApplyBackend, Backend, Basic, Log};
+use primitive_types::{H160, H256, U256};
+use tokio::runtime::Handle;
+
+use crate::state::{BlockEnvironment, StateProvider};
+
+/// Account changes of already executed transactions.
+#[derive(Default)]
+struct OverlayAccount {
+    basic: Basic,
+    code: Option<Vec<u8>>,
+    storage: HashMap<H256, H256>,
+    /// True if the storage of the account was wiped, so missing slots are empty.
+    reset_storage: bool,
+}
+
+/// EVM backend that reads the account state through a [`StateProvider`].
+///
+/// State changes applied to the backend are kept in memory on top of the provided state (deleted accounts are `None`).
+///
+/// The EVM backend is synchronous, so it must be used from a blocking task where the async
+/// state provider calls can be driven by the runtime handle.
+/// As the backend can't return errors, the first state provider error is stored and execution continues with default values.
+pub struct StateBackend<S: StateProvider> {
+    state_provider: Arc<S>,
+    handle: Handle,
+    environment: BlockEnvironment,
+    origin: H160,
+    overlay: HashMap<H160, Option<OverlayAccount>>,
+    error: RefCell<Option<String>>,
+}
+
+impl<S: StateProvider> StateBackend<S> {
+    pub fn new(
+        state_provider: Arc<S>,
+        handle: Handle,
+        environment: BlockEnvironment,
+        origin: H160,
+    ) -> Self {
+        Self {
+            state_provider,
+            handle,
+            environment,
+            origin,
+            overlay: HashMap::new(),
+            error: RefCell::new(None),
+        }
+    }
+
+    /// Returns the first state provider error that happened during execution (if any).
+    pub fn take_error(&self) -> Option<String> {
+        self.error.borrow_mut().take()
+    }
+
+    fn fetch<T: Default>(&self, future: impl Future<Output = Result<T, S::Error>>) -> T {
+        match self.handle.block_on(future) {
+            Ok(value) => value,
+            Err(error) => {
+                self.error.borrow_mut().get_or_insert(error.to_string());
+                T::default()
+            }
+        }
+    }
+}
+
+impl<S: StateProvider> Backend for StateBackend<S> {
+    fn gas_price(&self) -> U256 {
+        self.environment.base_fee_per_gas
+    }
+
+    fn origin(&self) -> H160 {
+        self.origin
+    }
+
+    fn block_hash(&self, _number: U256) -> H256 {
+        H256::default()
+    }
+
+    fn block_number(&self) -> U256 {
+        self.environment.number
+    }
+
+    fn block_coinbase(&self) -> H160 {
+        self.environment.coinbase
+    }
+
+    fn block_timestamp(&self) -> U256 {
+        self.environment.timestamp
+    }
+
+    fn block_difficulty(&self) -> U256 {
+        U256::zero()
+    }
+
+    fn block_randomness(&self) -> Option<H256> {
+        Some(self.environment.prev_randao)
+    }
+
+    fn block_gas_limit(&self) -> U256 {
+        self.environment.gas_limit
+    }
+
+    fn block_base_fee_per_gas(&self) -> U256 {
+        self.environment.base_fee_per_gas
+    }
+
+    fn chain_id(&self) -> U256 {
+        self.environment.chain_id
+    }
+
+    fn exists(&self, address: H160) -> bool {
+        let basic = self.basic(address);
+        !basic.balance.is_zero() || !basic.nonce.is_zero() || !self.code(address).is_empty()
+    }
+
+    fn basic(&self, address: H160) -> Basic {
+        match self.overlay.get(&address) {
+            Some(Some(account)) => return account.basic.clone(),
+            Some(None) => return Basic::default(),
+            None => {}
+        }
+        Basic {
+            balance: self.fetch(
+                self.state_provider
+                    .balance(address, self.environment.number),
+            ),
+            nonce: self.fetch(self.state_provider.nonce(address, self.environment.number)),
+        }
+    }
+
+    fn code(&self, address: H160) -> Vec<u8> {
+        match self.overlay.get(&address) {
+            Some(Some(OverlayAccount {
+                code: Some(code), ..
+            })) => return code.clone(),
+            Some(None) => return Vec::new(),
+            _ => {}
+        }
+        self.fetch(self.state_provider.code(address, self.environment.number))
+    }
+
+    fn storage(&self, address: H160, index: H256) -> H256 {
+        match self.overlay.get(&address) {
+            Some(Some(account)) => {
+                if let Some(value) = account.storage.get(&index) {
+                    return *value;
+                }
+                if account.reset_storage {
+                    return H256::default();
+                }
+            }
+            Some(None) => return H256::default(),
+            None => {}
+        }
+        self.fetch(
+            self.state_provider
+                .storage(address, index, self.environment.number),
+        )
+    }
+
+    fn transient_storage(&self, _address: H160, _index: H256) -> H256 {
+        H256::default()
+    }
+
+    fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+        Some(self.storage(address, index))
+    }
+}
+
+impl<S: StateProvider> ApplyBackend for StateBackend<S> {
+    fn apply<A, I, L>(&mut self, values: A, _logs: L, delete_empty: bool)
+    where
+        A: IntoIterator<Item = Apply<I>>,
+        I: IntoIterator<Item = (H256, H256)>,
+        L: IntoIterator<Item = Log>,
+    {
+        for apply in values {
+            match apply {
+                Apply::Modify {
+                    address,
+                    basic,
+                    code,
+                    storage,
+                    reset_storage,
+                } => {
+                    // a deleted account is recreated with empty storage,
+                    // missing slots of any other account are read from the state provider
+                    let deleted = matches!(self.overlay.get(&address), Some(None));
+                    let account = self
+                        .overlay
+                        .entry(address)
+                        .or_insert_with(|| None)
+                        .get_or_insert_with(|| OverlayAccount {
+                            reset_storage: deleted,
+                            ..Default::default()
+                        });
+                    account.basic = basic;
+                    if code.is_some() {
+                        account.code = code;
+                    }
+                    if reset_storage {
+                        account.storage.clear();
+                        account.reset_storage = true;
+                    }
+                    account.storage.extend(storage);
+
+                    let is_empty = self.basic(address).balance.is_zero()
+                        && self.basic(address).nonce.is_zero()
+                        && self.code(address).is_empty();
+                    if delete_empty && is_empty {
+                        self.overlay.insert(address, None);
+                    }
+                }
+                Apply::Delete { address } => {
+                    self.overlay.insert(address, None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::state::snapshot::{SnapshotAccount, SnapshotStateProvider, StateSnapshot};
+
+    use super::*;
+
+    const CONTRACT: H160 = H160::repeat_byte(0xcc);
+
+    fn slot(index: u64) -> H256 {
+        H256::from_low_u64_be(index)
+    }
+
+    /// Runs the given function with a backend on a state where slot 0 of the contract is 5.
+    async fn with_backend<R: Send + 'static>(
+        run: impl FnOnce(&mut StateBackend<SnapshotStateProvider>) -> R + Send + 'static,
+    ) -> R {
+        let mut accounts = BTreeMap::new();
+        accounts.insert(
+            CONTRACT,
+            SnapshotAccount {
+                storage: [(slot(0), slot(5))].into(),
+                ..Default::default()
+            },
+        );
+        let state_provider = Arc::new(SnapshotStateProvider::new(StateSnapshot {
+            accounts,
+            ..Default::default()
+        }));
+        let handle = Handle::current();
+        tokio::task::spawn_blocking(move || {
+            let environment = BlockEnvironment::default();
+            run(&mut StateBackend::new(
+                state_provider,
+                handle,
+                environment,
+                H160::zero(),
+            ))
+        })
+        .await
+        .unwrap()
+    }
+
+    fn modify(reset_storage: bool) -> Apply<Vec<(H256, H256)>> {
+        Apply::Modify {
+            address: CONTRACT,
+            basic: Basic {
+                balance: U256::one(),
+                nonce: U256::one(),
+            },
+            code: None,
+            storage: vec![(slot(1), slot(7))],
+            reset_storage,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn modified_account_keeps_untouched_slots() {
+        let (untouched, modified) = with_backend(|backend| {
+            backend.apply(vec![modify(false)], vec![], true);
+            (
+                backend.storage(CONTRACT, slot(0)),
+                backend.storage(CONTRACT, slot(1)),
+            )
+        })
+        .await;
+
+        assert_eq!(untouched, slot(5));
+        assert_eq!(modified, slot(7));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reset_storage_wipes_untouched_slots() {
+        let untouched = with_backend(|backend| {
+            backend.apply(vec![modify(true)], vec![], true);
+            backend.storage(CONTRACT, slot(0))
+        })
+        .await;
+
+        assert_eq!(untouched, H256::zero());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn recreated_account_has_empty_storage() {
+        let untouched = with_backend(|backend| {
+            backend.apply(
+                vec![Apply::Delete { address: CONTRACT }, modify(false)],
+                vec![],
+                true,
+            );
+            backend.storage(CONTRACT, slot(0))
+        })
+        .await;
+
+        assert_eq!(untouched, H256::zero());
+    }
+}
@@ -0,0 +1,418 @@
+use std::sync::Arc;
+
+use ethereum::TransactionAction;
+use evm::{
+    backend::{Apply, ApplyBackend, Backend},
+    executor::stack::{
+        Accessed, MemoryStackState, StackExecutor, StackState, StackSubstateMetadata,
+    },
+    gasometer::{self, Gasometer},
+    Config, CreateScheme, ExitReason, ExitSucceed,
+};
+use primitive_types::{H160, H256, U256};
+use thiserror::Error;
+use tokio::{runtime::Handle, task::JoinError};
+
+use crate::{
+    fee_estimator::{
+        precompile,
+        validation::{self, ValidationError, ValidationErrorReason},
+        Transaction,
+    },
+    state::{BlockEnvironment, StateProvider},
+};
+
+use backend::StateBackend;
+use precompiles::Precompiles;
+use tracer::{StructLog, Tracer};
+
+mod backend;
+mod precompiles;
+pub mod tracer;
+
+/// EVM configuration used for local execution.
+static CONFIG: Config = Config::cancun();
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Validation(#[from] ValidationError),
+    #[error("State provider error: {0}")]
+    StateProvider(String),
+    #[error("Task join error: {0}")]
+    TaskJoin(#[from] JoinError),
+}
+
+/// Result of a locally executed transaction.
+#[derive(Debug)]
+pub struct SimulationResult {
+    pub exit_reason: ExitReason,
+    /// Returned data (revert data in case of revert).
+    pub output: Vec<u8>,
+    /// Gas used by the transaction (refunds included).
+    pub gas_used: u64,
+    /// Address of the new contract in case of contract creation.
+    pub created_address: Option<H160>,
+    /// Addresses and storage slots accessed during execution (including the initially warm ones).
+    pub accessed: Accessed,
+    /// Coinbase address of the block the transaction was executed in.
+    pub coinbase: H160,
+    /// Executed operations, only collected if requested.
+    pub trace: Option<Vec<StructLog>>,
+}
+
+/// Transaction fields needed for execution.
+struct Execution {
+    caller: H160,
+    value: U256,
+    action: TransactionAction,
+    input: Vec<u8>,
+    gas_limit: u64,
+    access_list: Vec<(H160, Vec<H256>)>,
+}
+
+impl Execution {
+    fn new(transaction: &Transaction) -> Result<Self, Error> {
+        Ok(Self {
+            caller: transaction
+                .sender()
+                .ok_or_else(|| ValidationError::new("from", ValidationErrorReason::Missing))?,
+            value: transaction.value(),
+            action: transaction.action(),
+            input: validation::decode_hex("input", transaction.input())?,
+            gas_limit: validation::to_u64("gas_limit", transaction.gas_limit())?,
+            access_list: transaction
+                .access_list()
+                .into_iter()
+                .map(|item| (item.address, item.storage_keys))
+                .collect(),
+        })
+    }
+}
+
+/// Executes transactions locally on top of the state served by a [`StateProvider`].
+pub struct Simulator<S: StateProvider> {
+    state_provider: Arc<S>,
+}
+
+impl<S: StateProvider + 'static> Simulator<S> {
+    pub fn new(state_provider: Arc<S>) -> Self {
+        Self { state_provider }
+    }
+
+    pub fn state_provider(&self) -> &Arc<S> {
+        &self.state_provider
+    }
+
+    /// Executes the given transaction, none of the state changes are persisted.
+    /// The trace of the execution is collected only if `include_trace` is set.
+    pub async fn simulate(
+        &self,
+        transaction: &Transaction,
+        include_trace: bool,
+    ) -> Result<SimulationResult, Error> {
+        let environment = self.environment().await?;
+        let execution = Execution::new(transaction)?;
+
+        // calls to EOAs don't execute any bytecode
+        if let TransactionAction::Call(address) = execution.action {
+            let code = self
+                .state_provider
+                .code(address, environment.number)
+                .await
+                .map_err(|error| Error::StateProvider(error.to_string()))?;
+            if code.is_empty() && !precompile::is_precompile(&address) {
+                return Ok(Self::simulate_without_code(
+                    execution.caller,
+                    address,
+                    environment.coinbase,
+                    &execution.input,
+                    execution.gas_limit,
+                    execution.access_list,
+                    include_trace,
+                ));
+            }
+        }
+
+        let state_provider = self.state_provider.clone();
+        let handle = Handle::current();
+
+        tokio::task::spawn_blocking(move || {
+            let mut backend =
+                StateBackend::new(state_provider, handle, environment, execution.caller);
+            let result = Self::execute(&mut backend, execution, include_trace);
+
+            match backend.take_error() {
+                Some(error) => Err(Error::StateProvider(error)),
+                None => Ok(result),
+            }
+        })
+        .await?
+    }
+
+    /// Executes the given transactions in order on a shared state,
+    /// so each transaction sees the state changes of the previous ones (none of them are persisted).
+    pub async fn simulate_bundle(
+        &self,
+        transactions: &[Transaction],
+    ) -> Result<Vec<SimulationResult>, Error> {
+        let environment = self.environment().await?;
+        let executions = transactions
+            .iter()
+            .map(Execution::new)
+            .collect::<Result<Vec<_>, _>>()?;
+        let origin = executions
+            .first()
+            .map(|execution| execution.caller)
+            .unwrap_or_default();
+
+        let state_provider = self.state_provider.clone();
+        let handle = Handle::current();
+
+        tokio::task::spawn_blocking(move || {
+            let mut backend = StateBackend::new(state_provider, handle, environment, origin);
+            let mut results = Vec::with_capacity(executions.len());
+            for execution in executions {
+                results.push(Self::execute(&mut backend, execution, false));
+            }
+
+            match backend.take_error() {
+                Some(error) => Err(Error::StateProvider(error)),
+                None => Ok(results),
+            }
+        })
+        .await?
+    }
+
+    async fn environment(&self) -> Result<BlockEnvironment, Error> {
+        self.state_provider
+            .environment()
+            .await
+            .map_err(|error| Error::StateProvider(error.to_string()))
+    }
+
+    /// Executes a single transaction and applies its state changes to the backend.
+    fn execute(
+        backend: &mut StateBackend<S>,
+        execution: Execution,
+        include_trace: bool,
+    ) -> SimulationResult {
+        let Execution {
+            caller,
+            value,
+            action,
+            input,
+            gas_limit,
+            access_list,
+        } = execution;
+        let coinbase = backend.block_coinbase();
+        let metadata = StackSubstateMetadata::new(gas_limit, &CONFIG);
+        let state = MemoryStackState::new(metadata, backend);
+        let mut executor = StackExecutor::new_with_precompiles(state, &CONFIG, &Precompiles);
+
+        let tracer = Tracer::default();
+        let execute = || match action {
+            TransactionAction::Create => (
+                Some(executor.create_address(CreateScheme::Legacy { caller })),
+                executor.transact_create(caller, value, input, gas_limit, access_list),
+            ),
+            TransactionAction::Call(address) => (
+                None,
+                executor.transact_call(caller, address, value, input, gas_limit, access_list),
+            ),
+        };
+        let (created_address, (exit_reason, output)) = if include_trace {
+            tracer.trace(execute)
+        } else {
+            execute()
+        };
+        let gas_used = executor.used_gas();
+        let accessed = executor
+            .state()
+            .metadata()
+            .accessed()
+            .clone()
+            .unwrap_or_default();
+        let (applies, logs) = executor.into_state().deconstruct();
+        let applies = applies
+            .into_iter()
+            .map(|apply| match apply {
+                Apply::Modify {
+                    address,
+                    basic,
+                    code,
+                    storage,
+                    reset_storage,
+                } => Apply::Modify {
+                    address,
+                    basic,
+                    code,
+                    storage: storage.into_iter().collect::<Vec<_>>(),
+                    reset_storage,
+                },
+                Apply::Delete { address } => Apply::Delete { address },
+            })
+            .collect::<Vec<_>>();
+        let logs = logs.into_iter().collect::<Vec<_>>();
+        backend.apply(applies, logs, true);
+
+        SimulationResult {
+            exit_reason,
+            output,
+            gas_used,
+            created_address,
+            accessed,
+            coinbase,
+            trace: include_trace.then(|| tracer.into_logs()),
+        }
+    }
+
+    /// Returns the result of a call without any bytecode to execute (the intrinsic gas).
+    fn simulate_without_code(
+        caller: H160,
+        address: H160,
+        coinbase: H160,
+        input: &[u8],
+        gas_limit: u64,
+        access_list: Vec<(H160, Vec<H256>)>,
+        include_trace: bool,
+    ) -> SimulationResult {
+        let mut gasometer = Gasometer::new(gas_limit, &CONFIG);
+        let result =
+            gasometer.record_transaction(gasometer::call_transaction_cost(input, &access_list));
+        let exit_reason = match result {
+            Ok(()) => ExitReason::Succeed(ExitSucceed::Stopped),
+            Err(error) => ExitReason::Error(error),
+        };
+
+        let mut accessed = Accessed::default();
+        accessed.access_addresses([caller, address, coinbase].into_iter());
+        for (address, storage_keys) in access_list {
+            accessed.access_address(address);
+            accessed.access_storages(storage_keys.into_iter().map(|key| (address, key)));
+        }
+
+        SimulationResult {
+            exit_reason,
+            output: Vec::new(),
+            gas_used: gasometer.total_used_gas(),
+            created_address: None,
+            accessed,
+            coinbase,
+            trace: include_trace.then(Vec::new),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        fee_estimator::LegacyTransaction,
+        state::snapshot::{SnapshotAccount, SnapshotStateProvider, StateSnapshot},
+    };
+
+    use super::*;
+
+    const SENDER: H160 = H160::repeat_byte(0xaa);
+    const CONTRACT: H160 = H160::repeat_byte(0xcc);
+
+    fn simulator(code: &str) -> Simulator<SnapshotStateProvider> {
+        let mut accounts = BTreeMap::new();
+        accounts.insert(
+            CONTRACT,
+            SnapshotAccount {
+                code: hex::decode(code).unwrap().into(),
+                ..Default::default()
+            },
+        );
+        let snapshot = StateSnapshot {
+            accounts,
+            ..Default::default()
+        };
+        Simulator::new(Arc::new(SnapshotStateProvider::new(snapshot)))
+    }
+
+    fn call(to: H160, input: &str, from: Option<H160>) -> Transaction {
+        Transaction::Legacy(LegacyTransaction {
+            gas_price: U256::zero(),
+            gas_limit: U256::from(100_000),
+            input: input.to_string(),
+            action: TransactionAction::Call(to),
+            from,
+            value: U256::zero(),
+            runtime_code_size: None,
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn contract_calls_precompile() {
+        // STATICCALL(GAS, 0x02, 0, 0, 0, 32) POP RETURN(0, 32)
+        let simulator = simulator("602060006000600060025afa5060206000f3");
+        let result = simulator
+            .simulate(&call(CONTRACT, "", Some(SENDER)), false)
+            .await
+            .unwrap();
+
+        assert!(result.exit_reason.is_succeed());
+        assert_eq!(
+            hex::encode(result.output),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        // intrinsic gas + 5 PUSH1 + GAS + warm STATICCALL with memory expansion + SHA256 + POP + 2 PUSH1 + RETURN
+        assert_eq!(
+            result.gas_used,
+            21_000 + 5 * 3 + 2 + 100 + 3 + 60 + 2 + 2 * 3
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn precompile_is_charged_when_called_directly() {
+        let simulator = simulator("");
+        let result = simulator
+            .simulate(
+                &call(H160::from_low_u64_be(0x04), "0x0102", Some(SENDER)),
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert!(result.exit_reason.is_succeed());
+        assert_eq!(result.output, vec![0x01, 0x02]);
+        // intrinsic gas + 2 non-zero calldata bytes + identity of 1 word
+        assert_eq!(result.gas_used, 21_000 + 2 * 16 + 15 + 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn missing_sender_is_rejected() {
+        let simulator = simulator("");
+        let result = simulator.simulate(&call(CONTRACT, "", None), false).await;
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn bundle_reads_slots_untouched_by_previous_transactions() {
+        // without calldata: SSTORE(1, 7) STOP, otherwise: RETURN(SLOAD(0))
+        let mut simulator = simulator("36600a576007600155005b60005460005260206000f3");
+        let mut snapshot = simulator.state_provider().snapshot().clone();
+        snapshot
+            .accounts
+            .get_mut(&CONTRACT)
+            .unwrap()
+            .storage
+            .insert(H256::zero(), H256::from_low_u64_be(5));
+        simulator = Simulator::new(Arc::new(SnapshotStateProvider::new(snapshot)));
+
+        let results = simulator
+            .simulate_bundle(&[
+                call(CONTRACT, "", Some(SENDER)),
+                call(CONTRACT, "01", Some(SENDER)),
+            ])
+            .await
+            .unwrap();
+
+        assert!(results.iter().all(|result| result.exit_reason.is_succeed()));
+        assert_eq!(results[1].output, H256::from_low_u64_be(5).as_bytes());
+    }
+}
@@ -0,0 +1,485 @@
+use alloy::{
+    consensus::EnvKzgSettings,
+    primitives::{PrimitiveSignature, B256, U256 as AlloyU256},
+};
+use evm::{
+    executor::stack::{
+        IsPrecompileResult, PrecompileFailure, PrecompileHandle, PrecompileOutput, PrecompileSet,
+    },
+    ExitError, ExitSucceed,
+};
+use num_bigint::BigUint;
+use primitive_types::H160;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use substrate_bn::{pairing_batch, AffineG1, AffineG2, Fq, Fq2, Fr, Group, Gt, G1, G2};
+
+use crate::fee_estimator::precompile;
+
+const ECRECOVER_INPUT_LENGTH: usize = 128;
+const BN254_ADD_INPUT_LENGTH: usize = 128;
+const BN254_MUL_INPUT_LENGTH: usize = 96;
+const BN254_PAIRING_POINT_LENGTH: usize = 192;
+const POINT_EVALUATION_VERSIONED_HASH_VERSION: u8 = 0x01;
+/// `FIELD_ELEMENTS_PER_BLOB` and `BLS_MODULUS` as returned by the KZG point evaluation precompile.
+const POINT_EVALUATION_OUTPUT: [u8; 64] = {
+    let mut output = [0u8; 64];
+    output[30] = 0x10;
+    let modulus: [u8; 32] = [
+        0x73, 0xed, 0xa7, 0x53, 0x29, 0x9d, 0x7d, 0x48, 0x33, 0x39, 0xd8, 0x08, 0x09, 0xa1, 0xd8,
+        0x05, 0x53, 0xbd, 0xa4, 0x02, 0xff, 0xfe, 0x5b, 0xfe, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00,
+        0x00, 0x01,
+    ];
+    let mut index = 0;
+    while index < 32 {
+        output[32 + index] = modulus[index];
+        index += 1;
+    }
+    output
+};
+
+const BLAKE2F_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const BLAKE2F_SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// Precompiled contracts used for local execution, the gas is charged
+/// with the same pricing as the static estimation (see [`precompile::cost`]).
+///
+/// The Cancun precompiles (`0x01` - `0x0a`) are executed locally,
+/// calls to the BLS12-381 precompiles (`0x0b` - `0x11`) are charged but fail, as they are not implemented.
+pub struct Precompiles;
+
+impl PrecompileSet for Precompiles {
+    fn execute(
+        &self,
+        handle: &mut impl PrecompileHandle,
+    ) -> Option<Result<PrecompileOutput, PrecompileFailure>> {
+        let address = handle.code_address();
+        if !precompile::is_precompile(&address) {
+            return None;
+        }
+
+        let result = precompile::cost(&address, handle.input())
+            .map_err(|error| other_error(error.to_string()))
+            .and_then(|cost| Ok(handle.record_cost(cost)?))
+            .and_then(|_| run(&address, handle.input()))
+            .map(|output| PrecompileOutput {
+                exit_status: ExitSucceed::Returned,
+                output,
+            });
+        Some(result)
+    }
+
+    fn is_precompile(&self, address: H160, _remaining_gas: u64) -> IsPrecompileResult {
+        IsPrecompileResult::Answer {
+            is_precompile: precompile::is_precompile(&address),
+            extra_cost: 0,
+        }
+    }
+}
+
+/// Executes the given precompile, the input is already validated by the pricing.
+fn run(address: &H160, input: &[u8]) -> Result<Vec<u8>, PrecompileFailure> {
+    match address.as_bytes()[19] {
+        0x01 => Ok(ecrecover(input)),
+        0x02 => Ok(Sha256::digest(input).to_vec()),
+        0x03 => {
+            let mut output = vec![0u8; 12];
+            output.extend_from_slice(&Ripemd160::digest(input));
+            Ok(output)
+        }
+        0x04 => Ok(input.to_vec()),
+        0x05 => modexp(input),
+        0x06 => bn254_add(input),
+        0x07 => bn254_mul(input),
+        0x08 => bn254_pairing(input),
+        0x09 => Ok(blake2f(input)),
+        0x0a => point_evaluation(input),
+        _ => Err(other_error("BLS12-381 precompiles are not supported")),
+    }
+}
+
+/// Returns the address recovered from the signature, empty output if the signature is invalid.
+fn ecrecover(input: &[u8]) -> Vec<u8> {
+    let input = right_pad(input, ECRECOVER_INPUT_LENGTH);
+    let v = AlloyU256::from_be_slice(&input[32..64]);
+    let y_parity = if v == AlloyU256::from(27) {
+        false
+    } else if v == AlloyU256::from(28) {
+        true
+    } else {
+        return vec![];
+    };
+    let signature = PrimitiveSignature::new(
+        AlloyU256::from_be_slice(&input[64..96]),
+        AlloyU256::from_be_slice(&input[96..128]),
+        y_parity,
+    );
+    match signature.recover_address_from_prehash(&B256::from_slice(&input[..32])) {
+        Ok(address) => {
+            let mut output = vec![0u8; 12];
+            output.extend_from_slice(address.as_slice());
+            output
+        }
+        Err(_) => vec![],
+    }
+}
+
+fn modexp(input: &[u8]) -> Result<Vec<u8>, PrecompileFailure> {
+    let header = right_pad(input, 96);
+    let length = |offset: usize| {
+        usize::try_from(primitive_types::U256::from_big_endian(
+            &header[offset..offset + 32],
+        ))
+        .map_err(|_| ExitError::OutOfGas)
+    };
+    let (base_length, exponent_length, modulus_length) = (length(0)?, length(32)?, length(64)?);
+    if modulus_length == 0 {
+        return Ok(vec![]);
+    }
+
+    let data = input.get(96..).unwrap_or_default();
+    let data = right_pad(
+        data,
+        base_length
+            .saturating_add(exponent_length)
+            .saturating_add(modulus_length),
+    );
+    let base = BigUint::from_bytes_be(&data[..base_length]);
+    let exponent = BigUint::from_bytes_be(&data[base_length..base_length + exponent_length]);
+    let modulus = BigUint::from_bytes_be(&data[base_length + exponent_length..]);
+
+    let mut output = vec![0u8; modulus_length];
+    if modulus != BigUint::ZERO {
+        let result = base.modpow(&exponent, &modulus).to_bytes_be();
+        output[modulus_length - result.len()..].copy_from_slice(&result);
+    }
+    Ok(output)
+}
+
+fn bn254_add(input: &[u8]) -> Result<Vec<u8>, PrecompileFailure> {
+    let input = right_pad(input, BN254_ADD_INPUT_LENGTH);
+    let sum = read_g1_point(&input[..64])? + read_g1_point(&input[64..128])?;
+    Ok(encode_g1_point(sum))
+}
+
+fn bn254_mul(input: &[u8]) -> Result<Vec<u8>, PrecompileFailure> {
+    let input = right_pad(input, BN254_MUL_INPUT_LENGTH);
+    let point = read_g1_point(&input[..64])?;
+    // can't fail with a 32 bytes slice, the scalar is reduced
+    let scalar = Fr::from_slice(&input[64..96]).map_err(|_| invalid_point())?;
+    Ok(encode_g1_point(point * scalar))
+}
+
+fn bn254_pairing(input: &[u8]) -> Result<Vec<u8>, PrecompileFailure> {
+    let pairs = input
+        .chunks(BN254_PAIRING_POINT_LENGTH)
+        .map(|chunk| {
+            let a = read_g1_point(&chunk[..64])?;
+            let b_x = Fq2::new(read_fq(&chunk[96..128])?, read_fq(&chunk[64..96])?);
+            let b_y = Fq2::new(read_fq(&chunk[160..192])?, read_fq(&chunk[128..160])?);
+            let b = if b_x.is_zero() && b_y.is_zero() {
+                G2::zero()
+            } else {
+                AffineG2::new(b_x, b_y).map_err(|_| invalid_point())?.into()
+            };
+            Ok((a, b))
+        })
+        .collect::<Result<Vec<(G1, G2)>, PrecompileFailure>>()?;
+
+    let mut output = vec![0u8; 32];
+    output[31] = (pairing_batch(&pairs) == Gt::one()) as u8;
+    Ok(output)
+}
+
+fn read_fq(bytes: &[u8]) -> Result<Fq, PrecompileFailure> {
+    Fq::from_slice(bytes).map_err(|_| invalid_point())
+}
+
+/// Reads a G1 point from 64 bytes, `(0, 0)` is the point at infinity.
+fn read_g1_point(bytes: &[u8]) -> Result<G1, PrecompileFailure> {
+    let x = read_fq(&bytes[..32])?;
+    let y = read_fq(&bytes[32..64])?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1::zero());
+    }
+    AffineG1::new(x, y)
+        .map(Into::into)
+        .map_err(|_| invalid_point())
+}
+
+fn encode_g1_point(point: G1) -> Vec<u8> {
+    let mut output = vec![0u8; 64];
+    if let Some(point) = AffineG1::from_jacobian(point) {
+        // slices are 32 bytes long, so encoding can't fail
+        let _ = point.x().to_big_endian(&mut output[..32]);
+        let _ = point.y().to_big_endian(&mut output[32..]);
+    }
+    output
+}
+
+/// BLAKE2 compression function F as specified in EIP-152.
+fn blake2f(input: &[u8]) -> Vec<u8> {
+    let rounds = u32::from_be_bytes([input[0], input[1], input[2], input[3]]) as usize;
+    let read_words = |bytes: &[u8]| -> Vec<u64> {
+        bytes
+            .chunks(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap_or_default()))
+            .collect()
+    };
+    let mut h = read_words(&input[4..68]);
+    let m = read_words(&input[68..196]);
+    let t = read_words(&input[196..212]);
+    let last_block = input[212] == 1;
+
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(&h);
+    v[8..].copy_from_slice(&BLAKE2F_IV);
+    v[12] ^= t[0];
+    v[13] ^= t[1];
+    if last_block {
+        v[14] = !v[14];
+    }
+    for round in 0..rounds {
+        let s = &BLAKE2F_SIGMA[round % 10];
+        blake2f_mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        blake2f_mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        blake2f_mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        blake2f_mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        blake2f_mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        blake2f_mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        blake2f_mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        blake2f_mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+    for (index, word) in h.iter_mut().enumerate() {
+        *word ^= v[index] ^ v[index + 8];
+    }
+
+    h.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+fn blake2f_mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+/// Verifies a KZG proof as specified in EIP-4844.
+fn point_evaluation(input: &[u8]) -> Result<Vec<u8>, PrecompileFailure> {
+    let versioned_hash = &input[..32];
+    let commitment = &input[96..144];
+    let mut expected_hash = Sha256::digest(commitment);
+    expected_hash[0] = POINT_EVALUATION_VERSIONED_HASH_VERSION;
+    if versioned_hash != expected_hash.as_slice() {
+        return Err(other_error("mismatched versioned hash"));
+    }
+
+    let invalid_proof = |_| other_error("invalid KZG proof");
+    let verified = EnvKzgSettings::Default
+        .get()
+        .verify_kzg_proof(
+            &c_kzg::Bytes48::from_bytes(commitment).map_err(invalid_proof)?,
+            &c_kzg::Bytes32::from_bytes(&input[32..64]).map_err(invalid_proof)?,
+            &c_kzg::Bytes32::from_bytes(&input[64..96]).map_err(invalid_proof)?,
+            &c_kzg::Bytes48::from_bytes(&input[144..192]).map_err(invalid_proof)?,
+        )
+        .map_err(invalid_proof)?;
+    if !verified {
+        return Err(other_error("invalid KZG proof"));
+    }
+
+    Ok(POINT_EVALUATION_OUTPUT.to_vec())
+}
+
+/// Returns the input padded with zeros to at least the given length.
+fn right_pad(input: &[u8], length: usize) -> Vec<u8> {
+    let mut padded = input.to_vec();
+    if padded.len() < length {
+        padded.resize(length, 0);
+    }
+    padded
+}
+
+fn invalid_point() -> PrecompileFailure {
+    other_error("invalid point")
+}
+
+fn other_error(reason: impl Into<String>) -> PrecompileFailure {
+    PrecompileFailure::Error {
+        exit_status: ExitError::Other(reason.into().into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_precompile(address: u8, input: &str) -> Result<String, PrecompileFailure> {
+        let input = hex::decode(input).unwrap();
+        run(&H160::from_low_u64_be(address as u64), &input).map(hex::encode)
+    }
+
+    #[test]
+    fn ecrecover_recovers_signer() {
+        let input = "38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e\
+                     000000000000000000000000000000000000000000000000000000000000001b\
+                     38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e\
+                     789d1dd423d25f0772d2748d60f7e4b81bb14d086eba8e8e8efb6dcff8a4ae02";
+        assert_eq!(
+            run_precompile(0x01, input).unwrap(),
+            "000000000000000000000000ceaccac640adf55b2028469bd36ba501f28b699d"
+        );
+    }
+
+    #[test]
+    fn ecrecover_returns_nothing_for_invalid_v() {
+        let input = format!("{}{:0>64}", "00".repeat(32), "1d");
+        assert_eq!(run_precompile(0x01, &input).unwrap(), "");
+    }
+
+    #[test]
+    fn hash_precompiles() {
+        assert_eq!(
+            run_precompile(0x02, "").unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            run_precompile(0x03, "").unwrap(),
+            "0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31"
+        );
+        assert_eq!(run_precompile(0x04, "0102").unwrap(), "0102");
+    }
+
+    #[test]
+    fn modexp_fermat() {
+        // 3 ^ (p - 1) mod p = 1 (EIP-198 example)
+        let input = format!(
+            "{:0>64}{:0>64}{:0>64}03{}{}",
+            "1",
+            "20",
+            "20",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f"
+        );
+        assert_eq!(
+            run_precompile(0x05, &input).unwrap(),
+            format!("{:0>64}", "1")
+        );
+    }
+
+    #[test]
+    fn modexp_zero_modulus() {
+        let input = format!("{:0>64}{:0>64}{:0>64}030300", "1", "1", "1");
+        assert_eq!(run_precompile(0x05, &input).unwrap(), "00");
+        let input = format!("{:0>64}{:0>64}{:0>64}0303", "1", "1", "0");
+        assert_eq!(run_precompile(0x05, &input).unwrap(), "");
+    }
+
+    #[test]
+    fn bn254_add_and_mul() {
+        // generator (1, 2) doubled
+        let double_generator = "030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3\
+                                15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4";
+        let generator = format!("{:0>64}{:0>64}", "1", "2");
+        assert_eq!(
+            run_precompile(0x06, &format!("{generator}{generator}")).unwrap(),
+            double_generator
+        );
+        assert_eq!(
+            run_precompile(0x07, &format!("{generator}{:0>64}", "2")).unwrap(),
+            double_generator
+        );
+        // missing input is the point at infinity
+        assert_eq!(run_precompile(0x06, "").unwrap(), "00".repeat(64));
+        // (1, 1) is not on the curve
+        assert!(run_precompile(0x06, &format!("{:0>64}{:0>64}", "1", "1")).is_err());
+    }
+
+    #[test]
+    fn bn254_pairing_of_nothing_succeeds() {
+        assert_eq!(run_precompile(0x08, "").unwrap(), format!("{:0>64}", "1"));
+    }
+
+    #[test]
+    fn blake2f_eip152_vector() {
+        // EIP-152 test vector 5 (BLAKE2b of "abc")
+        let input = format!(
+            "0000000c\
+             48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5\
+             d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b\
+             6162630000000000{}\
+             03000000000000000000000000000000\
+             01",
+            "00".repeat(120)
+        );
+        assert_eq!(
+            run_precompile(0x09, &input).unwrap(),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+             7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+    }
+
+    #[test]
+    fn point_evaluation_of_zero_polynomial() {
+        // the commitment and the proof of the zero polynomial are the point at infinity
+        let infinity = format!("c0{}", "00".repeat(47));
+        let mut versioned_hash = Sha256::digest(hex::decode(&infinity).unwrap());
+        versioned_hash[0] = POINT_EVALUATION_VERSIONED_HASH_VERSION;
+        let input = format!(
+            "{}{}{}{infinity}{infinity}",
+            hex::encode(versioned_hash),
+            "00".repeat(32),
+            "00".repeat(32)
+        );
+        assert_eq!(
+            run_precompile(0x0a, &input).unwrap(),
+            hex::encode(POINT_EVALUATION_OUTPUT)
+        );
+
+        // y = 1 is not the value of the zero polynomial
+        let input = format!(
+            "{}{}{:0>64}{infinity}{infinity}",
+            hex::encode(versioned_hash),
+            "00".repeat(32),
+            "1"
+        );
+        assert!(run_precompile(0x0a, &input).is_err());
+    }
+
+    #[test]
+    fn point_evaluation_rejects_mismatched_versioned_hash() {
+        let infinity = format!("c0{}", "00".repeat(47));
+        let input = format!("{}{infinity}{infinity}", "00".repeat(96));
+        assert!(run_precompile(0x0a, &input).is_err());
+    }
+
+    #[test]
+    fn bls12_precompiles_fail() {
+        assert!(run_precompile(0x0b, &"00".repeat(256)).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use std::{cell::RefCell, rc::Rc};
+
+use primitive_types::H256;
+use serde::{Deserialize, Serialize};
+
+/// A single executed operation, in the same format as `debug_traceCall` struct logs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: usize,
+    pub op: String,
+    /// Remaining gas before executing the operation
+    pub gas: u64,
+    /// Gas cost of the operation (including memory expansion)
+    pub gas_cost: u64,
+    pub depth: usize,
+    /// Top of the stack before executing the operation
+    pub stack_top: Option<H256>,
+}
+
+#[derive(Default)]
+struct TraceState {
+    logs: Vec<StructLog>,
+    depth: usize,
+    /// True if the gas cost of the last step is not recorded yet.
+    pending_cost: bool,
+    /// True if the last step is a create operation whose frame is not entered yet.
+    pending_create: bool,
+}
+
+impl TraceState {
+    /// Records the cost of the last step, `memory_gas` is the total memory gas after the step (if it is changed).
+    fn record_cost(
+        &mut self,
+        cost: u64,
+        memory_gas: Option<u64>,
+        snapshot: Option<evm_gasometer::Snapshot>,
+    ) {
+        if !self.pending_cost {
+            return;
+        }
+        self.pending_cost = false;
+
+        let (Some(snapshot), Some(log)) = (snapshot, self.logs.last_mut()) else {
+            return;
+        };
+        // snapshots are taken before the cost is recorded
+        let memory_cost = memory_gas
+            .map(|memory_gas| memory_gas.saturating_sub(snapshot.memory_gas))
+            .unwrap_or_default();
+        log.gas = snapshot
+            .gas_limit
+            .saturating_sub(snapshot.used_gas)
+            .saturating_sub(snapshot.memory_gas);
+        log.gas_cost = cost + memory_cost;
+    }
+}
+
+/// Collects struct logs of an execution by listening to the EVM, runtime and gasometer events.
+#[derive(Default)]
+pub struct Tracer {
+    state: Rc<RefCell<TraceState>>,
+}
+
+impl Tracer {
+    /// Runs the given execution while collecting its trace.
+    pub fn trace<R>(&self, execute: impl FnOnce() -> R) -> R {
+        let mut evm_listener = EvmListener(self.state.clone());
+        let mut runtime_listener = RuntimeListener(self.state.clone());
+        let mut gasometer_listener = GasometerListener(self.state.clone());
+
+        let result = evm::tracing::using(&mut evm_listener, || {
+            evm_runtime::tracing::using(&mut runtime_listener, || {
+                evm_gasometer::tracing::using(&mut gasometer_listener, execute)
+            })
+        });
+        debug_assert_eq!(
+            self.state.borrow().depth,
+            0,
+            "Call frames are not balanced after the execution"
+        );
+        result
+    }
+
+    pub fn into_logs(self) -> Vec<StructLog> {
+        self.state.take().logs
+    }
+}
+
+/// Tracks call frames (depth).
+struct EvmListener(Rc<RefCell<TraceState>>);
+
+impl evm::tracing::EventListener for EvmListener {
+    fn event(&mut self, event: evm::tracing::Event<'_>) {
+        let mut state = self.0.borrow_mut();
+        match event {
+            evm::tracing::Event::Call { .. } | evm::tracing::Event::Create { .. } => {
+                state.pending_create = false;
+                state.depth += 1;
+            }
+            // a create rejected before entering its frame (e.g. init code cost) exits without a frame
+            evm::tracing::Event::Exit { .. } if state.pending_create => {
+                state.pending_create = false;
+            }
+            // a transaction rejected before entering its frame (e.g. intrinsic gas) exits without a frame
+            evm::tracing::Event::Exit { .. } if state.depth == 0 => {}
+            evm::tracing::Event::Exit { .. } => {
+                state.depth -= 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Records executed operations.
+struct RuntimeListener(Rc<RefCell<TraceState>>);
+
+impl evm_runtime::tracing::EventListener for RuntimeListener {
+    fn event(&mut self, event: evm_runtime::tracing::Event<'_>) {
+        if let evm_runtime::tracing::Event::StepResult {
+            result: Err(evm::Capture::Trap(opcode)),
+            ..
+        } = event
+        {
+            self.0.borrow_mut().pending_create =
+                matches!(*opcode, evm::Opcode::CREATE | evm::Opcode::CREATE2);
+        }
+        if let evm_runtime::tracing::Event::Step {
+            opcode,
+            position,
+            stack,
+            ..
+        } = event
+        {
+            let mut state = self.0.borrow_mut();
+            let log = StructLog {
+                pc: position.as_ref().copied().unwrap_or_default(),
+                op: format!("{:?}", evm_disassembler::Opcode::from_byte(opcode.as_u8())),
+                gas: 0,
+                gas_cost: 0,
+                depth: state.depth,
+                stack_top: stack.data().last().copied(),
+            };
+            state.logs.push(log);
+            state.pending_cost = true;
+        }
+    }
+}
+
+/// Records gas cost of the executed operations.
+struct GasometerListener(Rc<RefCell<TraceState>>);
+
+impl evm_gasometer::tracing::EventListener for GasometerListener {
+    fn event(&mut self, event: evm_gasometer::tracing::Event) {
+        let mut state = self.0.borrow_mut();
+        match event {
+            evm_gasometer::tracing::Event::RecordCost { cost, snapshot } => {
+                state.record_cost(cost, None, snapshot)
+            }
+            evm_gasometer::tracing::Event::RecordDynamicCost {
+                gas_cost,
+                memory_gas,
+                snapshot,
+                ..
+            } => state.record_cost(gas_cost, Some(memory_gas), snapshot),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use ethereum::TransactionAction;
+    use primitive_types::{H160, U256};
+
+    use crate::{
+        fee_estimator::{LegacyTransaction, Transaction},
+        simulator::Simulator,
+        state::snapshot::{SnapshotAccount, SnapshotStateProvider, StateSnapshot},
+    };
+
+    const SENDER: H160 = H160::repeat_byte(0xaa);
+    const CONTRACT: H160 = H160::repeat_byte(0xcc);
+    const CALLEE: H160 = H160::repeat_byte(0xdd);
+
+    async fn trace(code: &str, gas_limit: u64) -> Vec<super::StructLog> {
+        let mut accounts = BTreeMap::new();
+        for (address, code) in [(CONTRACT, code), (CALLEE, "60015000")] {
+            accounts.insert(
+                address,
+                SnapshotAccount {
+                    code: hex::decode(code).unwrap().into(),
+                    ..Default::default()
+                },
+            );
+        }
+        let snapshot = StateSnapshot {
+            accounts,
+            ..Default::default()
+        };
+        let simulator = Simulator::new(Arc::new(SnapshotStateProvider::new(snapshot)));
+        let transaction = Transaction::Legacy(LegacyTransaction {
+            gas_price: U256::zero(),
+            gas_limit: gas_limit.into(),
+            input: String::new(),
+            action: TransactionAction::Call(CONTRACT),
+            from: Some(SENDER),
+            value: U256::zero(),
+            runtime_code_size: None,
+        });
+        // the tracer asserts that the call frames are balanced after the execution
+        simulator
+            .simulate(&transaction, true)
+            .await
+            .unwrap()
+            .trace
+            .unwrap()
+    }
+
+    fn depths(logs: &[super::StructLog]) -> Vec<usize> {
+        logs.iter().map(|log| log.depth).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn nested_call_depth() {
+        // CALL(GAS, CALLEE, 0, 0, 0, 0, 0) STOP, the callee runs PUSH1 1 POP STOP
+        let code = format!("{}73{}5af100", "6000".repeat(5), hex::encode(CALLEE));
+        let logs = trace(&code, 100_000).await;
+
+        assert_eq!(
+            logs.iter().map(|log| log.op.as_str()).collect::<Vec<_>>(),
+            [
+                "PUSH1", "PUSH1", "PUSH1", "PUSH1", "PUSH1", "PUSH20", "GAS", "CALL", "PUSH1",
+                "POP", "STOP", "STOP"
+            ]
+        );
+        assert_eq!(depths(&logs), [1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 1]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rejected_create_keeps_depth() {
+        // CREATE(0, 0, 0xc001) with init code over the size limit, then STOP
+        let logs = trace("61c00160006000f000", 100_000).await;
+
+        assert_eq!(logs.last().unwrap().op, "STOP");
+        assert!(logs.iter().all(|log| log.depth == 1));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rejected_transaction_has_no_frames() {
+        // not enough gas for the intrinsic cost
+        let logs = trace("00", 20_000).await;
+
+        assert!(logs.is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+use std::convert::Infallible;
+
+use async_trait::async_trait;
+use primitive_types::{H160, H256, U256};
+use thiserror::Error;
+
+use super::{rpc, snapshot::SnapshotStateProvider, BlockEnvironment, StateProvider};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("RPC state provider error: {0}")]
+    Rpc(#[from] rpc::Error),
+}
+
+impl From<Infallible> for Error {
+    fn from(error: Infallible) -> Self {
+        match error {}
+    }
+}
+
+/// State provider that is selected at startup (through CLI arguments).
+#[derive(Clone)]
+pub enum AnyStateProvider {
+    Rpc(rpc::RpcStateProvider),
+    Snapshot(SnapshotStateProvider),
+}
+
+#[async_trait]
+impl StateProvider for AnyStateProvider {
+    type Error = Error;
+
+    async fn environment(&self) -> Result<BlockEnvironment, Self::Error> {
+        match self {
+            AnyStateProvider::Rpc(provider) => Ok(provider.environment().await?),
+            AnyStateProvider::Snapshot(provider) => Ok(provider.environment().await?),
+        }
+    }
+
+    async fn balance(&self, address: H160, block_number: U256) -> Result<U256, Self::Error> {
+        match self {
+            AnyStateProvider::Rpc(provider) => Ok(provider.balance(address, block_number).await?),
+            AnyStateProvider::Snapshot(provider) => {
+                Ok(provider.balance(address, block_number).await?)
+            }
+        }
+    }
+
+    async fn nonce(&self, address: H160, block_number: U256) -> Result<U256, Self::Error> {
+        match self {
+            AnyStateProvider::Rpc(provider) => Ok(provider.nonce(address, block_number).await?),
+            AnyStateProvider::Snapshot(provider) => {
+                Ok(provider.nonce(address, block_number).await?)
+            }
+        }
+    }
+
+    async fn code(&self, address: H160, block_number: U256) -> Result<Vec<u8>, Self::Error> {
+        match self {
+            AnyStateProvider::Rpc(provider) => Ok(provider.code(address, block_number).await?),
+            AnyStateProvider::Snapshot(provider) => {
+                Ok(provider.code(address, block_number).await?)
+            }
+        }
+    }
+
+    async fn storage(
+        &self,
+        address: H160,
+        index: H256,
+        block_number: U256,
+    ) -> Result<H256, Self::Error> {
+        match self {
+            AnyStateProvider::Rpc(provider) => {
+                Ok(provider.storage(address, index, block_number).await?)
+            }
+            AnyStateProvider::Snapshot(provider) => {
+                Ok(provider.storage(address, index, block_number).await?)
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use primitive_types::{H160, H256, U256};
+use serde::{Deserialize, Serialize};
+
+pub mod any;
+pub mod rpc;
+pub mod snapshot;
+
+/// Block level environment that is used when executing transactions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockEnvironment {
+    pub number: U256,
+    pub timestamp: U256,
+    pub coinbase: H160,
+    pub gas_limit: U256,
+    pub base_fee_per_gas: U256,
+    pub prev_randao: H256,
+    pub chain_id: U256,
+}
+
+/// The trait that all state providers must implement.
+/// It gives the chance to let the implementation handle where the account state comes from (Ethereum node, local snapshot etc...).
+#[async_trait]
+pub trait StateProvider: Send + Sync {
+    type Error: std::fmt::Display;
+
+    /// Returns the environment of the latest block, the state of a single execution
+    /// has to be read at its number, so it doesn't change while executing.
+    async fn environment(&self) -> Result<BlockEnvironment, Self::Error>;
+
+    /// Returns the balance of the given account in WEI at the given block.
+    async fn balance(&self, address: H160, block_number: U256) -> Result<U256, Self::Error>;
+
+    /// Returns the nonce of the given account at the given block.
+    async fn nonce(&self, address: H160, block_number: U256) -> Result<U256, Self::Error>;
+
+    /// Returns the code of the given account at the given block (empty for EOAs).
+    async fn code(&self, address: H160, block_number: U256) -> Result<Vec<u8>, Self::Error>;
+
+    /// Returns the value of the given storage slot of the given account at the given block.
+    async fn storage(
+        &self,
+        address: H160,
+        index: H256,
+        block_number: U256,
+    ) -> Result<H256, Self::Error>;
+}
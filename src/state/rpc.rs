@@ -0,0 +1,278 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap, HashMap},
+    fmt::Debug,
+    hash::Hash,
+    sync::Arc,
+};
+
+use alloy::{
+    primitives::Bytes,
+    rpc::client::RpcClient,
+    transports::{RpcError, TransportErrorKind},
+};
+use async_trait::async_trait;
+use log::debug;
+use primitive_types::{H160, H256, U256};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::{OnceCell, RwLock};
+
+use super::{BlockEnvironment, StateProvider};
+
+const LOG_TARGET: &str = "state::rpc";
+const LATEST_BLOCK_TAG: &str = "latest";
+
+/// Number of the latest blocks whose state is cached, so executions that started
+/// before a new block was seen keep using the cache.
+const CACHED_BLOCKS: usize = 2;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Ethereum client JSON-RPC transport error: {0}")]
+    RpcTransport(#[from] RpcError<TransportErrorKind>),
+}
+
+/// Block header fields returned by `eth_getBlockByNumber` that are needed for execution.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcBlock {
+    number: U256,
+    timestamp: U256,
+    miner: H160,
+    gas_limit: U256,
+    #[serde(default)]
+    base_fee_per_gas: U256,
+    #[serde(default)]
+    mix_hash: H256,
+}
+
+/// Account state fetched at a single block.
+#[derive(Default)]
+struct BlockState {
+    balances: HashMap<H160, U256>,
+    nonces: HashMap<H160, U256>,
+    codes: HashMap<H160, Bytes>,
+    storage: HashMap<(H160, H256), H256>,
+}
+
+/// Returns the same cache of a block for reading and for writing.
+type CacheAccessors<K, V> = (
+    fn(&BlockState) -> &HashMap<K, V>,
+    fn(&mut BlockState) -> &mut HashMap<K, V>,
+);
+
+/// State provider that lazily fetches account state through ethereum JSON RPC calls.
+///
+/// The state is fetched at the block number passed by the caller (the one of the environment the execution started with),
+/// so it stays consistent even if a new block is seen while executing.
+/// The fetched state is cached per block for the last [`CACHED_BLOCKS`] blocks.
+#[derive(Clone)]
+pub struct RpcStateProvider {
+    eth_client: RpcClient,
+    chain_id: Arc<OnceCell<U256>>,
+    blocks: Arc<RwLock<BTreeMap<U256, BlockState>>>,
+}
+
+impl RpcStateProvider {
+    pub fn new(eth_client: RpcClient) -> Self {
+        Self {
+            eth_client,
+            chain_id: Arc::new(OnceCell::new()),
+            blocks: Arc::new(RwLock::new(BTreeMap::new())),
+        }
+    }
+
+    /// Returns the cached value if there is any for the given block, otherwise fetches and caches it.
+    async fn cached<K, V, P>(
+        &self,
+        (cache, cache_mut): CacheAccessors<K, V>,
+        block_number: U256,
+        key: K,
+        method: &'static str,
+        params: impl FnOnce(String) -> P,
+    ) -> Result<V, Error>
+    where
+        K: Hash + Eq,
+        V: DeserializeOwned + Clone + Debug + Send + Sync + Unpin + 'static,
+        P: Serialize + Clone + Debug + Send + Sync + Unpin,
+    {
+        if let Some(value) = self
+            .blocks
+            .read()
+            .await
+            .get(&block_number)
+            .and_then(|state| cache(state).get(&key))
+        {
+            return Ok(value.clone());
+        }
+
+        let value: V = self
+            .eth_client
+            .request(method, params(format!("{block_number:#x}")))
+            .await?;
+
+        // blocks that are not cached (anymore) are not cached again
+        if let Some(state) = self.blocks.write().await.get_mut(&block_number) {
+            cache_mut(state).insert(key, value.clone());
+        }
+
+        Ok(value)
+    }
+}
+
+#[async_trait]
+impl StateProvider for RpcStateProvider {
+    type Error = Error;
+
+    async fn environment(&self) -> Result<BlockEnvironment, Self::Error> {
+        let block: RpcBlock = self
+            .eth_client
+            .request("eth_getBlockByNumber", (LATEST_BLOCK_TAG, false))
+            .await?;
+        let chain_id = *self
+            .chain_id
+            .get_or_try_init(|| async {
+                self.eth_client
+                    .request_noparams::<U256>("eth_chainId")
+                    .await
+            })
+            .await?;
+
+        let mut blocks = self.blocks.write().await;
+        if let Entry::Vacant(entry) = blocks.entry(block.number) {
+            debug!(target: LOG_TARGET, "New block {}, caching its state", block.number);
+            entry.insert(BlockState::default());
+            while blocks.len() > CACHED_BLOCKS {
+                blocks.pop_first();
+            }
+        }
+
+        Ok(BlockEnvironment {
+            number: block.number,
+            timestamp: block.timestamp,
+            coinbase: block.miner,
+            gas_limit: block.gas_limit,
+            base_fee_per_gas: block.base_fee_per_gas,
+            prev_randao: block.mix_hash,
+            chain_id,
+        })
+    }
+
+    async fn balance(&self, address: H160, block_number: U256) -> Result<U256, Self::Error> {
+        self.cached(
+            (|state| &state.balances, |state| &mut state.balances),
+            block_number,
+            address,
+            "eth_getBalance",
+            |block| (address, block),
+        )
+        .await
+    }
+
+    async fn nonce(&self, address: H160, block_number: U256) -> Result<U256, Self::Error> {
+        self.cached(
+            (|state| &state.nonces, |state| &mut state.nonces),
+            block_number,
+            address,
+            "eth_getTransactionCount",
+            |block| (address, block),
+        )
+        .await
+    }
+
+    async fn code(&self, address: H160, block_number: U256) -> Result<Vec<u8>, Self::Error> {
+        let code = self
+            .cached(
+                (|state| &state.codes, |state| &mut state.codes),
+                block_number,
+                address,
+                "eth_getCode",
+                |block| (address, block),
+            )
+            .await?;
+        Ok(code.to_vec())
+    }
+
+    async fn storage(
+        &self,
+        address: H160,
+        index: H256,
+        block_number: U256,
+    ) -> Result<H256, Self::Error> {
+        self.cached(
+            (|state| &state.storage, |state| &mut state.storage),
+            block_number,
+            (address, index),
+            "eth_getStorageAt",
+            |block| (address, index, block),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::rpc::client::ClientBuilder;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::test_utils::StubRpcServer;
+
+    async fn provider() -> (StubRpcServer, RpcStateProvider) {
+        let server = StubRpcServer::start(|method, _params| match method {
+            "eth_getBlockByNumber" => Some(json!({
+                "number": "0x10",
+                "timestamp": "0x1",
+                "miner": format!("{:#x}", H160::zero()),
+                "gasLimit": "0x1c9c380",
+                "baseFeePerGas": "0x3b9aca00",
+            })),
+            "eth_chainId" => Some(json!("0x1")),
+            "eth_getBalance" => Some(json!("0x64")),
+            _ => None,
+        })
+        .await;
+        let provider = RpcStateProvider::new(ClientBuilder::default().http(server.url.clone()));
+        (server, provider)
+    }
+
+    #[tokio::test]
+    async fn state_is_read_at_the_given_block() {
+        let (server, provider) = provider().await;
+        let environment = provider.environment().await.unwrap();
+        let address = H160::repeat_byte(1);
+
+        let balance = provider.balance(address, environment.number).await.unwrap();
+
+        assert_eq!(balance, U256::from(100));
+        let (_, params) = server
+            .requests()
+            .into_iter()
+            .find(|(method, _)| method == "eth_getBalance")
+            .unwrap();
+        assert_eq!(params, json!([format!("{address:#x}"), "0x10"]));
+    }
+
+    #[tokio::test]
+    async fn state_is_cached_per_block() {
+        let (server, provider) = provider().await;
+        let environment = provider.environment().await.unwrap();
+        let address = H160::repeat_byte(1);
+
+        provider.balance(address, environment.number).await.unwrap();
+        provider.balance(address, environment.number).await.unwrap();
+        assert_eq!(server.count("eth_getBalance"), 1);
+
+        // another block is fetched again (and not cached as it wasn't seen through the environment)
+        provider.balance(address, U256::from(15)).await.unwrap();
+        provider.balance(address, U256::from(15)).await.unwrap();
+        assert_eq!(server.count("eth_getBalance"), 3);
+        let blocks: Vec<Value> = server
+            .requests()
+            .into_iter()
+            .filter(|(method, _)| method == "eth_getBalance")
+            .map(|(_, params)| params[1].clone())
+            .collect();
+        assert_eq!(blocks, [json!("0x10"), json!("0xf"), json!("0xf")]);
+    }
+}
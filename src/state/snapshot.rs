@@ -0,0 +1,183 @@
+use std::{collections::BTreeMap, convert::Infallible, fs, io, path::Path, sync::Arc};
+
+use alloy::primitives::Bytes;
+use async_trait::async_trait;
+use primitive_types::{H160, H256, U256};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{BlockEnvironment, StateProvider};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    IO(#[from] io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A single account in the state snapshot.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotAccount {
+    #[serde(default)]
+    pub balance: U256,
+    #[serde(default)]
+    pub nonce: U256,
+    #[serde(default)]
+    pub code: Bytes,
+    #[serde(default)]
+    pub storage: BTreeMap<H256, H256>,
+}
+
+/// State of accounts at a given block, missing accounts and storage slots are considered to be empty.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    #[serde(default)]
+    pub environment: BlockEnvironment,
+    #[serde(default)]
+    pub accounts: BTreeMap<H160, SnapshotAccount>,
+}
+
+/// State provider that serves account state from a local snapshot, without any external calls.
+/// The snapshot has the state of a single block, so the requested block number is ignored.
+#[derive(Clone)]
+pub struct SnapshotStateProvider {
+    snapshot: Arc<StateSnapshot>,
+}
+
+impl SnapshotStateProvider {
+    pub fn new(snapshot: StateSnapshot) -> Self {
+        Self {
+            snapshot: Arc::new(snapshot),
+        }
+    }
+
+    /// Loads the snapshot from the given JSON file.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let snapshot = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Ok(Self::new(snapshot))
+    }
+
+    pub fn snapshot(&self) -> &StateSnapshot {
+        &self.snapshot
+    }
+
+    fn account(&self, address: &H160) -> Option<&SnapshotAccount> {
+        self.snapshot.accounts.get(address)
+    }
+}
+
+#[async_trait]
+impl StateProvider for SnapshotStateProvider {
+    type Error = Infallible;
+
+    async fn environment(&self) -> Result<BlockEnvironment, Self::Error> {
+        Ok(self.snapshot.environment.clone())
+    }
+
+    async fn balance(&self, address: H160, _block_number: U256) -> Result<U256, Self::Error> {
+        Ok(self
+            .account(&address)
+            .map(|account| account.balance)
+            .unwrap_or_default())
+    }
+
+    async fn nonce(&self, address: H160, _block_number: U256) -> Result<U256, Self::Error> {
+        Ok(self
+            .account(&address)
+            .map(|account| account.nonce)
+            .unwrap_or_default())
+    }
+
+    async fn code(&self, address: H160, _block_number: U256) -> Result<Vec<u8>, Self::Error> {
+        Ok(self
+            .account(&address)
+            .map(|account| account.code.to_vec())
+            .unwrap_or_default())
+    }
+
+    async fn storage(
+        &self,
+        address: H160,
+        index: H256,
+        _block_number: U256,
+    ) -> Result<H256, Self::Error> {
+        Ok(self
+            .account(&address)
+            .and_then(|account| account.storage.get(&index).copied())
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum::TransactionAction;
+
+    use crate::{
+        fee_estimator::{LegacyTransaction, Transaction},
+        simulator::Simulator,
+    };
+
+    use super::*;
+
+    fn fixture() -> SnapshotStateProvider {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/state_snapshot.json");
+        SnapshotStateProvider::load(&path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn loads_snapshot_file() {
+        let provider = fixture();
+        let contract: H160 = "0x4838b106fce9647bdf1e7877bf73ce8b0bad5f97"
+            .parse()
+            .unwrap();
+
+        let environment = provider.environment().await.unwrap();
+        let block = environment.number;
+        assert_eq!(block, U256::from(20_000_000));
+        assert_eq!(environment.base_fee_per_gas, U256::from(1_000_000_000));
+        assert_eq!(
+            provider.balance(contract, block).await.unwrap(),
+            U256::from(1_000_000_000_000_000_000u64)
+        );
+        assert_eq!(provider.nonce(contract, block).await.unwrap(), U256::one());
+        assert_eq!(
+            provider
+                .storage(contract, H256::zero(), block)
+                .await
+                .unwrap(),
+            H256::from_low_u64_be(5)
+        );
+        // missing accounts are empty
+        assert!(provider.code(H160::zero(), block).await.unwrap().is_empty());
+        assert_eq!(
+            provider.balance(H160::zero(), block).await.unwrap(),
+            U256::zero()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn executes_call_on_snapshot() {
+        let contract: H160 = "0x4838b106fce9647bdf1e7877bf73ce8b0bad5f97"
+            .parse()
+            .unwrap();
+        let simulator = Simulator::new(Arc::new(fixture()));
+        let transaction = Transaction::Legacy(LegacyTransaction {
+            gas_price: U256::one(),
+            gas_limit: U256::from(100_000),
+            input: String::new(),
+            action: TransactionAction::Call(contract),
+            from: Some(H160::repeat_byte(0xaa)),
+            value: U256::zero(),
+            runtime_code_size: None,
+        });
+
+        let result = simulator.simulate(&transaction, false).await.unwrap();
+
+        assert!(result.exit_reason.is_succeed());
+        // the contract returns storage slot 0
+        assert_eq!(result.output, H256::from_low_u64_be(5).as_bytes());
+        // intrinsic gas + PUSH1 + cold SLOAD + PUSH1 + MSTORE (with memory expansion) + 2 PUSH1 + RETURN
+        assert_eq!(result.gas_used, 21_000 + 3 + 2_100 + 3 + 3 + 3 + 2 * 3);
+    }
+}
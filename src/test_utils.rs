@@ -0,0 +1,197 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use axum::{extract::State, routing::post, Json, Router};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{UnixListener, UnixStream},
+};
+use tokio_util::sync::CancellationToken;
+
+type Responder = dyn Fn(&str, &Value) -> Option<Value> + Send + Sync;
+
+#[derive(Clone)]
+struct StubState {
+    respond: Arc<Responder>,
+    requests: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+/// JSON-RPC server answering requests on a local port through the given function,
+/// methods it returns `None` for are answered with an error.
+pub struct StubRpcServer {
+    pub url: url::Url,
+    requests: Arc<Mutex<Vec<(String, Value)>>>,
+    cancel_token: CancellationToken,
+}
+
+impl StubRpcServer {
+    pub async fn start(
+        respond: impl Fn(&str, &Value) -> Option<Value> + Send + Sync + 'static,
+    ) -> Self {
+        let state = StubState {
+            respond: Arc::new(respond),
+            requests: Default::default(),
+        };
+        let requests = state.requests.clone();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let cancel_token = CancellationToken::new();
+        let app = Router::new().route("/", post(handler)).with_state(state);
+        let shutdown = cancel_token.clone();
+        tokio::spawn(async move {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async move { shutdown.cancelled().await })
+                .await
+        });
+
+        Self {
+            url,
+            requests,
+            cancel_token,
+        }
+    }
+
+    /// Returns the received requests as (method, params).
+    pub fn requests(&self) -> Vec<(String, Value)> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Returns the number of received requests of the given method.
+    pub fn count(&self, method: &str) -> usize {
+        self.requests()
+            .iter()
+            .filter(|(received, _)| received == method)
+            .count()
+    }
+}
+
+impl Drop for StubRpcServer {
+    fn drop(&mut self) {
+        self.cancel_token.cancel();
+    }
+}
+
+/// JSON-RPC server answering requests on the given unix socket (like the IPC socket of a node) through the given function,
+/// methods it returns `None` for are answered with an error.
+pub struct StubIpcServer {
+    requests: Arc<Mutex<Vec<(String, Value)>>>,
+    cancel_token: CancellationToken,
+}
+
+impl StubIpcServer {
+    pub fn start(
+        path: &Path,
+        respond: impl Fn(&str, &Value) -> Option<Value> + Send + Sync + 'static,
+    ) -> Self {
+        let state = StubState {
+            respond: Arc::new(respond),
+            requests: Default::default(),
+        };
+        let requests = state.requests.clone();
+        let listener = UnixListener::bind(path).unwrap();
+        let cancel_token = CancellationToken::new();
+        let shutdown = cancel_token.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Ok((stream, _)) = listener.accept() => {
+                        tokio::spawn(serve_ipc(stream, state.clone(), shutdown.clone()));
+                    }
+                    _ = shutdown.cancelled() => break,
+                }
+            }
+        });
+
+        Self {
+            requests,
+            cancel_token,
+        }
+    }
+
+    /// Returns the number of received requests of the given method.
+    pub fn count(&self, method: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(received, _)| received == method)
+            .count()
+    }
+}
+
+impl Drop for StubIpcServer {
+    fn drop(&mut self) {
+        self.cancel_token.cancel();
+    }
+}
+
+/// Answers the JSON values received on the stream, requests are not delimited (only concatenated).
+async fn serve_ipc(mut stream: UnixStream, state: StubState, cancel_token: CancellationToken) {
+    let mut buffer = vec![];
+    let mut chunk = [0; 4096];
+    loop {
+        let read = tokio::select! {
+            read = stream.read(&mut chunk) => read,
+            _ = cancel_token.cancelled() => return,
+        };
+        match read {
+            Ok(0) | Err(_) => return,
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+        }
+        let mut requests = serde_json::Deserializer::from_slice(&buffer).into_iter::<Value>();
+        let mut responses = vec![];
+        for request in requests.by_ref() {
+            match request {
+                Ok(request) => responses.push(respond(&state, &request)),
+                // the rest of the request is not received yet
+                Err(_) => break,
+            }
+        }
+        let consumed = requests.byte_offset();
+        buffer.drain(..consumed);
+        for response in responses {
+            if stream
+                .write_all(response.to_string().as_bytes())
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+fn respond(state: &StubState, request: &Value) -> Value {
+    match request {
+        Value::Array(batch) => batch
+            .iter()
+            .map(|request| respond(state, request))
+            .collect(),
+        request => {
+            let method = request["method"].as_str().unwrap_or_default();
+            let params = request.get("params").cloned().unwrap_or(Value::Null);
+            state
+                .requests
+                .lock()
+                .unwrap()
+                .push((method.to_string(), params.clone()));
+            match (state.respond)(method, &params) {
+                Some(result) => json!({"jsonrpc": "2.0", "id": request["id"], "result": result}),
+                None => json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": {"code": -32601, "message": "method not found"}
+                }),
+            }
+        }
+    }
+}
+
+async fn handler(State(state): State<StubState>, Json(request): Json<Value>) -> Json<Value> {
+    Json(respond(&state, &request))
+}